@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN long_break_interval INTEGER NOT NULL DEFAULT 4;
@@ -0,0 +1,10 @@
+CREATE TABLE sessions (
+    id INTEGER PRIMARY KEY,
+    task_id INTEGER,
+    started_at DATETIME NOT NULL,
+    ended_at DATETIME NULL,
+    pomos_finished INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(task_id) REFERENCES tasks(id)
+);
+
+ALTER TABLE cycles ADD COLUMN session_id INTEGER REFERENCES sessions(id);
@@ -0,0 +1,23 @@
+-- The pomo currently running, if any. There's only ever one row.
+CREATE TABLE active_timer (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    task_id INTEGER,
+    session_id INTEGER NOT NULL,
+    phase TEXT NOT NULL,
+    started_at DATETIME NOT NULL,
+    dur_secs INTEGER NOT NULL,
+    paused_secs INTEGER NOT NULL DEFAULT 0,
+    paused_at DATETIME NULL,
+    session_pomos INTEGER NOT NULL,
+    work_secs INTEGER NOT NULL,
+    short_break_secs INTEGER NOT NULL,
+    long_break_secs INTEGER NOT NULL,
+    long_break_interval INTEGER NOT NULL,
+    -- Last time the pomo was known to be going, so the time after a crash
+    -- isn't counted towards it.
+    seen_at DATETIME NOT NULL,
+    FOREIGN KEY(task_id) REFERENCES tasks(id),
+    FOREIGN KEY(session_id) REFERENCES sessions(id)
+);
+
+ALTER TABLE cycles ADD COLUMN actual_secs INTEGER NULL;
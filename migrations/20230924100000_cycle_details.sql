@@ -0,0 +1,17 @@
+-- Every phase now gets a row, written when it starts and finished when it
+-- ends. Rows with a NULL outcome are still running.
+ALTER TABLE cycles ADD COLUMN kind TEXT NOT NULL DEFAULT 'work';
+ALTER TABLE cycles ADD COLUMN started_at DATETIME NULL;
+ALTER TABLE cycles ADD COLUMN ended_at DATETIME NULL;
+ALTER TABLE cycles ADD COLUMN planned_secs INTEGER NULL;
+ALTER TABLE cycles ADD COLUMN paused_secs INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE cycles ADD COLUMN outcome TEXT NULL;
+
+-- before this, only finished work phases were recorded
+UPDATE cycles SET
+    -- created_at is UTC, while the new columns hold local time
+    ended_at = datetime(created_at, 'localtime'),
+    planned_secs = (SELECT work_secs FROM tasks WHERE tasks.id = cycles.task_id),
+    outcome = 'completed';
+
+ALTER TABLE active_timer ADD COLUMN cycle_id INTEGER NULL REFERENCES cycles(id);
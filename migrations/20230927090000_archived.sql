@@ -0,0 +1,3 @@
+-- Archived tasks are hidden from the task lists, but their cycles still
+-- count towards stats.
+ALTER TABLE tasks ADD COLUMN archived DATETIME NULL;
@@ -0,0 +1,2 @@
+-- Time added to (or, if negative, taken off) a cycle while it was running.
+ALTER TABLE cycles ADD COLUMN extended_secs INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Seconds between a cycle ending and the user starting the next one, when
+-- cycles only move on once acknowledged.
+ALTER TABLE cycles ADD COLUMN ack_secs INTEGER NULL;
+-- Set while a finished cycle is waiting for the user to start the next one.
+ALTER TABLE active_timer ADD COLUMN waiting_since DATETIME NULL;
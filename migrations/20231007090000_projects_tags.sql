@@ -0,0 +1,21 @@
+-- Each task can belong to one project, and have any number of tags. Names
+-- are matched ignoring case.
+CREATE TABLE projects (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE tags (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE task_tags (
+    task_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY(task_id, tag_id),
+    FOREIGN KEY(task_id) REFERENCES tasks(id),
+    FOREIGN KEY(tag_id) REFERENCES tags(id)
+);
+
+ALTER TABLE tasks ADD COLUMN project_id INTEGER NULL REFERENCES projects(id);
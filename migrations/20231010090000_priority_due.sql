@@ -0,0 +1,3 @@
+-- 0 for no priority, then 1 (low) to 3 (high).
+ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN due DATE NULL;
@@ -0,0 +1,2 @@
+-- Pomos the user expects a task to take, if they've said.
+ALTER TABLE tasks ADD COLUMN estimated_pomos INTEGER NULL;
@@ -0,0 +1,2 @@
+-- A subtask points at the task it's part of, which can be a subtask itself.
+ALTER TABLE tasks ADD COLUMN parent_id INTEGER NULL REFERENCES tasks(id);
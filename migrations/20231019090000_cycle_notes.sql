@@ -0,0 +1,2 @@
+-- What the user wrote down about a work cycle once it was over, if anything.
+ALTER TABLE cycles ADD COLUMN note TEXT NULL;
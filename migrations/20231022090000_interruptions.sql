@@ -0,0 +1,10 @@
+-- Interruptions logged during a work cycle: `internal` (the user's own urge to
+-- do something else) or `external` (someone or something else).
+CREATE TABLE interruptions (
+    id INTEGER PRIMARY KEY,
+    cycle_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT NULL,
+    logged_at DATETIME NOT NULL,
+    FOREIGN KEY(cycle_id) REFERENCES cycles(id)
+);
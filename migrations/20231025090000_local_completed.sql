@@ -0,0 +1,7 @@
+-- Completion and archive times used to be saved with their UTC offset, which
+-- makes DATE() give the UTC day. Store them in local time like every other
+-- column.
+UPDATE tasks SET completed = datetime(completed, 'localtime')
+WHERE completed GLOB '*[+-][0-9][0-9]:[0-9][0-9]';
+UPDATE tasks SET archived = datetime(archived, 'localtime')
+WHERE archived GLOB '*[+-][0-9][0-9]:[0-9][0-9]';
@@ -1,4 +1,7 @@
-use clap::{Args, Parser, Subcommand};
+use crate::{db::Interruption, tasks::Priority};
+use chrono::NaiveDate;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -7,6 +10,9 @@ use clap::{Args, Parser, Subcommand};
     about = "A poggers-as-hell terminal UI pomodoro timer"
 )]
 pub struct Cli {
+    /// Path to config file [default: ~/.config/pogodoro/config.toml]
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -14,15 +20,45 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Command {
     /// Lists incomplete tasks
-    List,
+    List(List),
     /// Adds task to DB
     Add(Add),
+    /// Changes the description or durations of a task with given ID
+    Edit(Edit),
     /// Completes a task with given ID
     Complete(Complete),
+    /// Marks a completed task with given ID as not completed
+    Reopen(Reopen),
+    /// Deletes a task with given ID
+    Rm(Rm),
+    /// Hides a task with given ID from the task lists, keeping its stats
+    Archive(Archive),
     /// Start a pomodoro session working on task with given ID
     WorkOn(WorkOn),
     /// Starts a (non-default) pomo session
     Start(Start),
+    /// Runs the timer in the background, to be controlled by the commands below
+    Daemon,
+    /// Shows the running pomo, e.g. for a status bar
+    Status(Status),
+    /// Pauses the running pomo
+    Pause,
+    /// Resumes a paused or interrupted pomo
+    Resume,
+    /// Skips to the next cycle of the running pomo
+    Skip,
+    /// Adds minutes to the current cycle of the running pomo, or takes them off if negative
+    Extend(Extend),
+    /// Logs an interruption to the work cycle of the running pomo
+    Interrupt(Interrupt),
+    /// Stops the running pomo, logging the current cycle as abandoned
+    Stop,
+    /// Reports pomos and focus time per day, week, month and task
+    Stats(Stats),
+    /// Exports tasks or cycles as CSV or JSON
+    Export(Export),
+    /// Adds tasks in bulk from a CSV, JSON or todo.txt file
+    Import(Import),
 }
 
 #[derive(Args)]
@@ -31,20 +67,65 @@ pub struct Complete {
     pub id: i64,
 }
 
+#[derive(Args)]
+pub struct Reopen {
+    /// IDs can be listed with `pogodoro list`
+    pub id: i64,
+}
+
+#[derive(Args)]
+pub struct Rm {
+    /// IDs can be listed with `pogodoro list`
+    pub id: i64,
+    /// Whether the task's cycles are kept (as unattributed) or deleted with it
+    #[arg(long, value_enum)]
+    pub cycles: CycleAction,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CycleAction {
+    Keep,
+    Delete,
+}
+
+#[derive(Args)]
+pub struct Interrupt {
+    /// Whether it was your own urge to do something else, or someone else
+    #[arg(value_enum)]
+    pub kind: Interruption,
+    /// What it was about
+    pub reason: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Archive {
+    /// IDs can be listed with `pogodoro list`
+    pub id: i64,
+}
+
 #[derive(Args)]
 pub struct Start {
-    /// Duration of each working session in minutes
-    pub work_mins: u64,
-    /// Duration of each short break in minutes
-    pub short_break_mins: u64,
-    /// Duration of each long break in minutes
-    pub long_break_mins: u64,
+    /// Duration of each working session in minutes [default: from config]
+    pub work_mins: Option<u64>,
+    /// Duration of each short break in minutes [default: from config]
+    pub short_break_mins: Option<u64>,
+    /// Duration of each long break in minutes [default: from config]
+    pub long_break_mins: Option<u64>,
+    /// Number of pomos before each long break [default: from config]
+    #[arg(short = 'i', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub long_break_interval: Option<u32>,
+    /// Start the pomo in the daemon without opening the UI
+    #[arg(short, long)]
+    pub detach: bool,
 }
 
 #[derive(Args)]
 pub struct WorkOn {
     /// IDs can be listed with `pogodoro list`
     pub id: i64,
+    /// Start the pomo in the daemon without opening the UI
+    #[arg(short, long)]
+    pub detach: bool,
 }
 
 #[derive(Args)]
@@ -56,10 +137,193 @@ pub struct Task {
 #[derive(Args)]
 pub struct Add {
     pub desc: String,
+    /// Duration of each working session in minutes [default: from config]
+    pub work_mins: Option<u64>,
+    /// Duration of each short break in minutes [default: from config]
+    pub short_break_mins: Option<u64>,
+    /// Duration of each long break in minutes [default: from config]
+    pub long_break_mins: Option<u64>,
+    /// Number of pomos before each long break [default: from config]
+    #[arg(short = 'i', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub long_break_interval: Option<u32>,
+    /// Project the task belongs to, e.g. a client
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Tag for the task; can be given more than once
+    #[arg(short, long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// How urgent the task is
+    #[arg(long, value_enum)]
+    pub priority: Option<Priority>,
+    /// Date the task is due (YYYY-MM-DD)
+    #[arg(long)]
+    pub due: Option<NaiveDate>,
+    /// Number of pomos the task is expected to take
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub estimate: Option<u32>,
+    /// ID of the task to add this as a subtask of
+    #[arg(long, value_name = "ID")]
+    pub parent: Option<i64>,
+}
+
+#[derive(Args)]
+pub struct Edit {
+    /// IDs can be listed with `pogodoro list`
+    pub id: i64,
+    /// New description
+    #[arg(short, long)]
+    pub desc: Option<String>,
     /// Duration of each working session in minutes
-    pub work_mins: u64,
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub work: Option<u64>,
     /// Duration of each short break in minutes
-    pub short_break_mins: u64,
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub short: Option<u64>,
     /// Duration of each long break in minutes
-    pub long_break_mins: u64,
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub long: Option<u64>,
+    /// Number of pomos before each long break
+    #[arg(short = 'i', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub long_break_interval: Option<u32>,
+    /// New project, or "" to take the task out of its project
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Tag for the task, replacing its current tags; can be given more than once
+    #[arg(short, long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Remove all of the task's tags
+    #[arg(long, conflicts_with = "tags")]
+    pub clear_tags: bool,
+    /// New priority, or none to clear it
+    #[arg(long, value_enum)]
+    pub priority: Option<Priority>,
+    /// New due date (YYYY-MM-DD)
+    #[arg(long)]
+    pub due: Option<NaiveDate>,
+    /// Remove the task's due date
+    #[arg(long, conflicts_with = "due")]
+    pub no_due: bool,
+    /// New number of pomos the task is expected to take
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub estimate: Option<u32>,
+    /// Remove the task's estimate
+    #[arg(long, conflicts_with = "estimate")]
+    pub no_estimate: bool,
+    /// ID of the task to make this a subtask of
+    #[arg(long, value_name = "ID")]
+    pub parent: Option<i64>,
+    /// Stop the task being a subtask
+    #[arg(long, conflicts_with = "parent")]
+    pub no_parent: bool,
+}
+
+#[derive(Args)]
+pub struct List {
+    /// Order tasks by due date or priority, rather than when they were added
+    #[arg(long, value_enum)]
+    pub sort: Option<SortBy>,
+    #[command(flatten)]
+    pub filter: Filter,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortBy {
+    Due,
+    Priority,
+}
+
+/// Narrows tasks, or the cycles worked on them, down to a project or tag.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Filter {
+    /// Only include tasks in this project
+    #[arg(short, long)]
+    pub project: Option<String>,
+    /// Only include tasks with this tag
+    #[arg(short, long)]
+    pub tag: Option<String>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.project.is_none() && self.tag.is_none()
+    }
+}
+
+#[derive(Args)]
+pub struct Extend {
+    /// Minutes to add, e.g. 5 or -1
+    #[arg(allow_negative_numbers = true)]
+    pub mins: i64,
+}
+
+#[derive(Args)]
+pub struct Status {
+    /// Template with any of {phase}, {remaining}, {clock}, {elapsed}, {task},
+    /// {set}, {pomos}, {percentage} and {paused}; prints an empty line when
+    /// no pomo is running
+    #[arg(short, long)]
+    pub format: Option<String>,
+    /// Print JSON for a waybar custom module, with text, tooltip, class and percentage
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct Stats {
+    /// Only count cycles finished on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Only count cycles finished on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+    #[command(flatten)]
+    pub filter: Filter,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("table").args(["tasks", "cycles"])))]
+pub struct Export {
+    #[arg(short, long, value_enum)]
+    pub format: Format,
+    /// Export tasks
+    #[arg(long)]
+    pub tasks: bool,
+    /// Export finished cycles, with their task's description (default)
+    #[arg(long)]
+    pub cycles: bool,
+    /// Only export cycles which ended on or after this date, or tasks which are
+    /// incomplete or were completed on or after it (YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Args)]
+pub struct Import {
+    /// CSV or JSON shaped like `pogodoro export --tasks`, or todo.txt
+    pub file: PathBuf,
+    /// Format of the file [default: guessed from its extension]
+    #[arg(short, long, value_enum)]
+    pub format: Option<ImportFormat>,
+    /// Show what would be imported without importing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+    Todo,
 }
@@ -0,0 +1,292 @@
+use serde::Deserialize;
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// User configuration, read from `config.toml` at startup.
+///
+/// Every field has a default, so the file (and any table or key in it)
+/// is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub durations: Durations,
+    pub timer: Timer,
+    pub database: Database,
+    pub notifications: Notifications,
+    pub ui: Ui,
+    pub hooks: Hooks,
+    /// File given with `--config`, passed on to the daemon when starting one.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+/// Defaults used for tasks and sessions which don't specify their own.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Durations {
+    pub work_mins: u64,
+    pub short_break_mins: u64,
+    pub long_break_mins: u64,
+    /// Number of pomos to finish before taking a long break.
+    pub long_break_interval: u32,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Self {
+            work_mins: 25,
+            short_break_mins: 5,
+            long_break_mins: 15,
+            long_break_interval: 4,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timer {
+    /// Wait for the user once a cycle is up, rather than starting the next
+    /// one straight away.
+    pub manual_advance: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Database {
+    /// Location of the SQLite database. A leading `~/` is expanded to `$HOME`.
+    pub path: PathBuf,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self {
+            path: config_dir().join("records.db"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notifications {
+    /// Send a notification each time the pomo changes cycle.
+    pub enabled: bool,
+    /// Tried in order until one of them manages to deliver the notification.
+    pub backends: Vec<Backend>,
+    /// Run by the `command` backend, with the message in `POGO_TITLE` and
+    /// `POGO_BODY`.
+    pub command: Option<String>,
+    /// Length of the snooze offered on desktop notifications, which goes back
+    /// to the phase that just ended. `0` leaves the action out.
+    pub snooze_mins: u64,
+    /// With `timer.manual_advance`, how often to remind the user that the next
+    /// cycle is waiting for them. `0` only notifies once.
+    pub reminder_mins: u64,
+    /// Messages for the start of each phase. `{task}`, `{phase}`, `{pomos}`
+    /// (including those on subtasks), `{estimate}`, `{set}` and `{next}` are
+    /// filled in.
+    pub work: String,
+    pub short_break: String,
+    pub long_break: String,
+    /// Message for a phase which is over, with `timer.manual_advance`.
+    pub waiting: String,
+    /// Message for a work phase which takes the task over its estimated pomos.
+    pub over_estimate: String,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            backends: vec![Backend::Desktop, Backend::Bell, Backend::Log],
+            command: None,
+            snooze_mins: 2,
+            reminder_mins: 5,
+            work: "Time to work on {task}!".into(),
+            short_break: "Short break time! {pomos} pomos finished on {task}.".into(),
+            long_break: "Long break time! {pomos} pomos finished on {task}.".into(),
+            waiting: "{phase} over! Start {next} when you're ready.".into(),
+            over_estimate: "{task} is over its estimate: {pomos}/{estimate} pomos finished.".into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Desktop,
+    /// Rings the bell of the terminal the daemon was started from.
+    Bell,
+    Command,
+    Log,
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ui {
+    /// How often the UI redraws, in milliseconds.
+    pub tick_rate_ms: u64,
+    /// Show the "Pomos over time" chart on the tasks page.
+    pub show_barchart: bool,
+    /// Number of days of history the chart holds.
+    pub barchart_days: usize,
+    /// Ask for a note on what got done at the end of each work cycle, while
+    /// the timer is open.
+    pub note_prompt: bool,
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self {
+            tick_rate_ms: 250,
+            show_barchart: true,
+            barchart_days: 30,
+            note_prompt: true,
+        }
+    }
+}
+
+/// Shell commands run when the pomo changes, with details about it in
+/// `POGO_*` environment variables.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub work_start: Option<String>,
+    pub work_end: Option<String>,
+    pub break_start: Option<String>,
+    pub break_end: Option<String>,
+    pub pause: Option<String>,
+    pub resume: Option<String>,
+    pub task_complete: Option<String>,
+    /// Hooks still running after this many seconds are killed.
+    pub timeout_secs: u64,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            work_start: None,
+            work_end: None,
+            break_start: None,
+            break_end: None,
+            pause: None,
+            resume: None,
+            task_complete: None,
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, err) => write!(f, "couldn't read {}: {}", path.display(), err),
+            Self::Parse(path, err) => write!(f, "couldn't parse {}: {}", path.display(), err),
+            Self::Invalid(path, msg) => write!(f, "invalid config in {}: {}", path.display(), msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the config at `path`, or at the default location if `None`.
+    ///
+    /// A missing file at the default location isn't an error, but a missing
+    /// file that was asked for explicitly is.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (config_dir().join("config.toml"), false),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(err) => return Err(ConfigError::Read(path, err)),
+        };
+        let mut config: Self =
+            toml::from_str(&text).map_err(|err| ConfigError::Parse(path.clone(), err))?;
+        config.database.path = expand_home(&config.database.path);
+        config.path = required.then_some(path.clone());
+        config
+            .validate()
+            .map_err(|msg| ConfigError::Invalid(path, msg))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let durations = &self.durations;
+        for (name, mins) in [
+            ("work_mins", durations.work_mins),
+            ("short_break_mins", durations.short_break_mins),
+            ("long_break_mins", durations.long_break_mins),
+        ] {
+            if mins == 0 {
+                return Err(format!("durations.{} must be at least 1", name));
+            }
+        }
+        if durations.long_break_interval == 0 {
+            return Err("durations.long_break_interval must be at least 1".into());
+        }
+        if self.database.path.as_os_str().is_empty() {
+            return Err("database.path must not be empty".into());
+        }
+        if self.ui.tick_rate_ms == 0 {
+            return Err("ui.tick_rate_ms must be at least 1".into());
+        }
+        if self.ui.barchart_days == 0 {
+            return Err("ui.barchart_days must be at least 1".into());
+        }
+        if self.notifications.command.is_none()
+            && self
+                .notifications
+                .backends
+                .iter()
+                .any(|backend| matches!(backend, Backend::Command))
+        {
+            return Err("notifications.command must be set to use the command backend".into());
+        }
+        if self.hooks.timeout_secs == 0 {
+            return Err("hooks.timeout_secs must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+/// Sets the config used for the rest of the program. Only the first call has
+/// any effect.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The config passed to [`init`], or the defaults if it hasn't been called.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+pub fn config_dir() -> PathBuf {
+    let mut path = env::var("HOME").unwrap();
+    path.push_str("/.config/pogodoro");
+    path.into()
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => PathBuf::from(env::var("HOME").unwrap()).join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
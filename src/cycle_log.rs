@@ -0,0 +1,206 @@
+use crate::{
+    db::{self, LoggedCycle},
+    pomodoro::centered_rect,
+    states::{AppResult, State},
+    tasks::TasksState,
+};
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::io;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    prelude::{Alignment, CrosstermBackend},
+    style::{Color, Modifier, Style},
+    text::Text,
+    widgets::{
+        block::Title, Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Table, TableState,
+    },
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// How many of the latest work cycles the log shows.
+const LOG_LENGTH: i64 = 200;
+const HELP_TEXT: &str = "[j] / [k] - move down / up
+[e] / [enter] - write a note on the selected cycle
+[q] / [esc] - return to tasks page";
+
+/// A one-line box for writing down what got done in a work cycle.
+pub struct NotePrompt {
+    cycle_id: i64,
+    text: String,
+}
+
+impl NotePrompt {
+    pub fn new(cycle_id: i64, text: String) -> Self {
+        Self { cycle_id, text }
+    }
+
+    /// Handles a key, returning whether the prompt is finished with. Enter
+    /// saves the note (clearing it if blank) and Esc leaves it as it was.
+    pub async fn handle_key(&mut self, event: KeyEvent) -> sqlx::Result<bool> {
+        match event.code {
+            KeyCode::Char(c) => self.text.push(c),
+            KeyCode::Backspace => {
+                self.text.pop();
+            }
+            KeyCode::Enter => {
+                let note = self.text.trim();
+                db::set_cycle_note(self.cycle_id, (!note.is_empty()).then_some(note)).await?;
+                return Ok(true);
+            }
+            KeyCode::Esc => return Ok(true),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Draws the prompt over whatever is in `area`, which should be 3 high.
+    pub fn render_on<B: Backend>(&self, frame: &mut Frame<'_, B>, area: Rect) {
+        let prompt = Paragraph::new(self.text.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .block(
+                Block::default()
+                    .title("What did you get done? [enter] save, [esc] skip")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            );
+        frame.render_widget(Clear, area);
+        frame.render_widget(prompt, area);
+        frame.set_cursor(area.x + self.text.width() as u16 + 1, area.y + 1);
+    }
+}
+
+/// Lists the latest work cycles along with their notes, which can be written
+/// or changed after the fact.
+pub struct CycleLog {
+    cycles: Vec<LoggedCycle>,
+    state: TableState,
+    note: Option<NotePrompt>,
+    show_help: bool,
+}
+
+#[async_trait]
+impl State for CycleLog {
+    async fn tick(&mut self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn should_finish(&self) -> bool {
+        false
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
+        let area = frame.size();
+        let header = Row::new(["Ended", "Task", "Time", "Outcome", "Note"].map(|h| {
+            Cell::from(Text::styled(
+                h,
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC),
+            ))
+        }))
+        .bottom_margin(1);
+        let rows = self.cycles.iter().map(|cycle| {
+            Row::new([
+                Cell::from(cycle.ended_at.format("%d/%m %H:%M").to_string()),
+                Cell::from(cycle.task_desc.clone().unwrap_or("(no task)".into())),
+                Cell::from(format!("{}m", cycle.actual_secs.unwrap_or(0) / 60)),
+                Cell::from(cycle.outcome.clone()),
+                Cell::from(cycle.note.clone().unwrap_or_default()),
+            ])
+        });
+        let table = Table::new(rows)
+            .header(header)
+            .block(
+                Block::default()
+                    .title(Title::from("Cycle log [?] help").alignment(Alignment::Center))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red))
+            .widths(&[
+                Constraint::Length(12),
+                Constraint::Percentage(25),
+                Constraint::Length(5),
+                Constraint::Length(10),
+                Constraint::Percentage(60),
+            ]);
+        frame.render_stateful_widget(table, area, &mut self.state);
+
+        if let Some(note) = &self.note {
+            note.render_on(frame, centered_rect(60.min(area.width), 3, area));
+        } else if self.show_help {
+            let help_chunk = centered_rect(52, 5, area);
+            let help_text = Paragraph::new(HELP_TEXT)
+                .block(
+                    Block::default()
+                        .title("Help")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(Clear, help_chunk);
+            frame.render_widget(help_text, help_chunk);
+        }
+    }
+
+    async fn handle_key_event(mut self: Box<Self>, event: KeyEvent) -> AppResult<Box<dyn State>> {
+        if let Some(note) = &mut self.note {
+            if note.handle_key(event).await? {
+                // show the note as saved
+                let selected = self.state.selected();
+                let mut log = Self::new().await?;
+                log.state.select(selected);
+                return Ok(Box::new(log));
+            }
+            return Ok(self);
+        }
+        match event.code {
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.move_selection(self.cycles.len().saturating_sub(1))
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(cycle) = self.state.selected().map(|i| &self.cycles[i]) {
+                    self.note = Some(NotePrompt::new(
+                        cycle.id,
+                        cycle.note.clone().unwrap_or_default(),
+                    ));
+                }
+            }
+            KeyCode::Char('?') => self.show_help = !self.show_help,
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Box::new(TasksState::new().await?)),
+            _ => {}
+        }
+        Ok(self)
+    }
+}
+
+impl CycleLog {
+    pub async fn new() -> sqlx::Result<Self> {
+        let cycles = db::read_cycle_log(LOG_LENGTH).await?;
+        let mut state = TableState::default();
+        state.select((!cycles.is_empty()).then_some(0));
+        Ok(Self {
+            cycles,
+            state,
+            note: None,
+            show_help: false,
+        })
+    }
+
+    /// Moves the selection `by` rows down, wrapping around.
+    fn move_selection(&mut self, by: usize) {
+        if self.cycles.is_empty() {
+            return;
+        }
+        let selected = self
+            .state
+            .selected()
+            .map_or(0, |i| (i + by) % self.cycles.len());
+        self.state.select(Some(selected));
+    }
+}
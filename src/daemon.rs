@@ -0,0 +1,428 @@
+use crate::{
+    db::{self, Interruption, Interruptions},
+    pomodoro::{format_remaining, Pomodoro, PomodoroState},
+    states::AppResult,
+    tasks::Task,
+};
+use flexi_logger::{FileSpec, Logger};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    signal::{
+        self,
+        unix::{signal, SignalKind},
+    },
+    sync::Mutex,
+    time,
+};
+
+/// How often the daemon checks whether the current cycle is up.
+const TICK: Duration = Duration::from_millis(500);
+
+type Slot = Arc<Mutex<Option<Pomodoro>>>;
+
+/// A command for the daemon, sent as one line of JSON, e.g.
+/// `{"cmd":"work_on","id":3}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Reports on the running pomo without changing it.
+    Status,
+    /// Starts a pomo on the task with this ID.
+    WorkOn {
+        id: i64,
+    },
+    /// Starts a pomo which isn't attached to a task.
+    Start {
+        work_secs: u64,
+        short_break_secs: u64,
+        long_break_secs: u64,
+        long_break_interval: u32,
+    },
+    Pause,
+    Resume,
+    /// Cuts the current cycle short and moves on to the next one.
+    Skip,
+    /// Adds time to the current phase, or takes it off if negative.
+    Extend {
+        secs: i64,
+    },
+    /// Goes back to the phase which just ended, with `secs` more from now.
+    Snooze {
+        secs: u64,
+    },
+    /// Logs an interruption against the current work cycle.
+    Interrupt {
+        kind: Interruption,
+        reason: Option<String>,
+    },
+    /// Ends the session, logging the current cycle as abandoned.
+    Stop,
+    /// Like `Stop`, but also marks the task as completed.
+    Complete,
+}
+
+/// The daemon's reply to each request, sent as one line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    /// `status` is `None` when no pomo is running.
+    Ok {
+        status: Option<Status>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Snapshot of the running pomo.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub phase: PomodoroState,
+    /// Row in `cycles` recording the current phase.
+    pub cycle_id: i64,
+    pub task_id: Option<u32>,
+    pub task: Option<String>,
+    pub duration_secs: u64,
+    pub elapsed_secs: u64,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    /// Logged during the current cycle.
+    pub interruptions: Interruptions,
+    /// Whether the phase is over and the next one is waiting to be started,
+    /// with `timer.manual_advance`.
+    pub waiting: bool,
+    pub next_phase: PomodoroState,
+    /// Pomos finished over the task's lifetime, including on its subtasks.
+    pub pomos_finished: u32,
+    pub estimated_pomos: Option<u32>,
+    /// Rough time until the estimated pomos are finished, if there's an estimate.
+    pub eta_secs: Option<u64>,
+    /// Position within the current set of pomos, which ends with a long break.
+    pub set_position: u32,
+    pub long_break_interval: u32,
+    /// Whether the daemon running the pomo is gone, leaving it to be picked
+    /// up with `pogodoro resume`. It's paused where it was last seen going.
+    #[serde(default)]
+    pub interrupted: bool,
+}
+
+impl From<&Pomodoro> for Status {
+    fn from(pomodoro: &Pomodoro) -> Self {
+        let duration = pomodoro.current.duration();
+        let elapsed = pomodoro.current.elapsed().min(duration);
+        Self {
+            phase: pomodoro.state,
+            cycle_id: pomodoro.cycle_id,
+            task_id: pomodoro.task.id,
+            task: pomodoro.task.desc.clone(),
+            duration_secs: duration.as_secs(),
+            elapsed_secs: elapsed.as_secs(),
+            remaining_secs: (duration - elapsed).as_secs_f64().ceil() as u64,
+            paused: pomodoro.current.is_paused(),
+            interruptions: pomodoro.interruptions,
+            waiting: pomodoro.is_waiting(),
+            next_phase: pomodoro.next_state(),
+            pomos_finished: pomodoro.task.total_pomos(),
+            estimated_pomos: pomodoro.task.estimated_pomos,
+            eta_secs: pomodoro.eta_secs(),
+            set_position: pomodoro.set_position(),
+            long_break_interval: pomodoro.task.long_break_interval,
+            interrupted: false,
+        }
+    }
+}
+
+impl Status {
+    /// One line summary, e.g. `Work on write report: 12m5s to go (2/4 in this set)`.
+    pub fn summary(&self) -> String {
+        if self.interrupted {
+            return format!(
+                "{}{}: interrupted{}, resume it with `pogodoro resume` ({}/{} in this set)",
+                self.phase,
+                self.task
+                    .as_deref()
+                    .map_or(String::new(), |desc| format!(" on {}", desc)),
+                if self.waiting {
+                    String::new()
+                } else {
+                    format!(" with {} to go", format_remaining(self.remaining_secs))
+                },
+                self.set_position,
+                self.long_break_interval,
+            );
+        }
+        if self.waiting {
+            return format!(
+                "{} over{}: start {} with `pogodoro skip` ({}/{} in this set)",
+                self.phase,
+                self.task
+                    .as_deref()
+                    .map_or(String::new(), |desc| format!(" on {}", desc)),
+                self.next_phase.to_string().to_lowercase(),
+                self.set_position,
+                self.long_break_interval,
+            );
+        }
+        format!(
+            "{}{}: {} to go ({}/{} in this set{}){}",
+            self.phase,
+            self.task
+                .as_deref()
+                .map_or(String::new(), |desc| format!(" on {}", desc)),
+            format_remaining(self.remaining_secs),
+            self.set_position,
+            self.long_break_interval,
+            match self.interruptions {
+                Interruptions {
+                    internal: 0,
+                    external: 0,
+                } => String::new(),
+                Interruptions { internal, external } => {
+                    format!(", interrupted {}' {}-", internal, external)
+                }
+            },
+            if self.paused { ", paused" } else { "" }
+        )
+    }
+}
+
+/// The socket sits next to the DB, so each DB gets its own daemon.
+pub fn socket_path() -> PathBuf {
+    db::path().with_file_name("pogodoro.sock")
+}
+
+/// Runs the daemon until it's interrupted or terminated, picking up any pomo
+/// left running by a previous run of the app.
+pub async fn run() -> AppResult<()> {
+    let path = socket_path();
+    if is_running().await {
+        return Err(format!("a daemon is already listening on {}", path.display()).into());
+    }
+    // left behind by a daemon which didn't shut down cleanly
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    let _logger = Logger::try_with_env_or_str("info")?
+        .log_to_file(
+            FileSpec::default()
+                .directory(path.parent().unwrap())
+                .basename("pogodoro-daemon")
+                .suppress_timestamp(),
+        )
+        .append()
+        .start()?;
+    info!("listening on {}", path.display());
+
+    let pomodoro = match db::read_active_timer().await? {
+        Some(active) => Some(Pomodoro::restore(active).await?),
+        None => None,
+    };
+    let slot: Slot = Arc::new(Mutex::new(pomodoro));
+    tokio::spawn(tick(slot.clone()));
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    // outlive the terminal the daemon was started from
+    let mut hangup = signal(SignalKind::hangup())?;
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, slot.clone()));
+                }
+                Err(err) => error!("couldn't accept connection: {}", err),
+            },
+            _ = hangup.recv() => {}
+            _ = terminate.recv() => break,
+            _ = signal::ctrl_c() => break,
+        }
+    }
+    // the pomo is saved in the DB, so the next daemon picks it up from here
+    info!("shutting down");
+    if let Some(pomodoro) = slot.lock().await.as_mut() {
+        pomodoro.mark_seen().await?;
+    }
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+async fn tick(slot: Slot) {
+    let mut interval = time::interval(TICK);
+    loop {
+        interval.tick().await;
+        if let Some(pomodoro) = slot.lock().await.as_mut() {
+            if let Err(err) = pomodoro.tick().await {
+                error!("{}", err);
+            }
+        }
+    }
+}
+
+async fn serve(stream: UnixStream, slot: Slot) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let mut pomodoro = slot.lock().await;
+                match apply(&mut pomodoro, request).await {
+                    Ok(()) => Response::Ok {
+                        status: pomodoro.as_ref().map(Status::from),
+                    },
+                    Err(err) => Response::Error {
+                        message: err.to_string(),
+                    },
+                }
+            }
+            Err(err) => Response::Error {
+                message: format!("invalid request: {}", err),
+            },
+        };
+        let mut reply = serde_json::to_string(&response).unwrap();
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn apply(slot: &mut Option<Pomodoro>, request: Request) -> AppResult<()> {
+    let task = match request {
+        Request::Status => {
+            if let Some(pomodoro) = slot.as_mut() {
+                pomodoro.current.update();
+            }
+            return Ok(());
+        }
+        Request::WorkOn { .. } | Request::Start { .. } if slot.is_some() => {
+            return Err("a pomo is already running, stop it first with `pogodoro stop`".into())
+        }
+        Request::WorkOn { id } => db::read_task(id).await?,
+        // the CLI checks these, but other clients of the socket might not
+        Request::Start { work_secs: 0, .. }
+        | Request::Start {
+            short_break_secs: 0,
+            ..
+        }
+        | Request::Start {
+            long_break_secs: 0, ..
+        } => return Err("durations must be more than zero".into()),
+        Request::Start {
+            long_break_interval: 0,
+            ..
+        } => return Err("long_break_interval must be at least 1".into()),
+        Request::Start {
+            work_secs,
+            short_break_secs,
+            long_break_secs,
+            long_break_interval,
+        } => Task {
+            work_secs,
+            short_break_secs,
+            long_break_secs,
+            long_break_interval,
+            ..Task::default()
+        },
+        request => {
+            let pomodoro = slot.as_mut().ok_or("no pomo is running")?;
+            match request {
+                Request::Pause => pomodoro.set_paused(true).await?,
+                Request::Resume => pomodoro.set_paused(false).await?,
+                Request::Skip => pomodoro.skip().await?,
+                Request::Extend { secs } => pomodoro.extend(secs).await?,
+                Request::Snooze { secs } => pomodoro.snooze(secs).await?,
+                Request::Interrupt { kind, reason } => {
+                    pomodoro.interrupt(kind, reason.as_deref()).await?
+                }
+                Request::Stop => {
+                    pomodoro.abandon().await?;
+                    *slot = None;
+                }
+                Request::Complete => {
+                    pomodoro.complete().await?;
+                    *slot = None;
+                }
+                _ => unreachable!(),
+            }
+            return Ok(());
+        }
+    };
+    *slot = Some(Pomodoro::new(task).await?);
+    Ok(())
+}
+
+/// Sends `request` to the daemon, returning the running pomo afterwards.
+pub async fn send(request: &Request) -> AppResult<Option<Status>> {
+    let stream = UnixStream::connect(socket_path())
+        .await
+        .map_err(|_| "the daemon isn't running, start it with `pogodoro daemon`")?;
+    let (reader, mut writer) = stream.into_split();
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    let mut reply = String::new();
+    BufReader::new(reader).read_line(&mut reply).await?;
+    match serde_json::from_str(&reply)? {
+        Response::Ok { status } => Ok(status),
+        Response::Error { message } => Err(message.into()),
+    }
+}
+
+pub async fn is_running() -> bool {
+    UnixStream::connect(socket_path()).await.is_ok()
+}
+
+/// Starts a daemon in the background unless one is running already, and
+/// waits until it's taking requests.
+pub async fn ensure_running() -> AppResult<()> {
+    if is_running().await {
+        return Ok(());
+    }
+    let mut command = Command::new(env::current_exe()?);
+    if let Some(path) = &crate::config::get().path {
+        command.arg("--config").arg(path);
+    }
+    command
+        .arg("daemon")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // keep it out of the terminal's process group, so it isn't killed with it
+        .process_group(0)
+        .spawn()?;
+    for _ in 0..50 {
+        time::sleep(Duration::from_millis(100)).await;
+        if is_running().await {
+            return Ok(());
+        }
+    }
+    Err("the daemon didn't start, run `pogodoro daemon` to see why".into())
+}
+
+/// Sends `request` on behalf of a subcommand and prints what's running afterwards.
+pub async fn control(request: Request) -> AppResult<()> {
+    // a fresh daemon also picks up an interrupted pomo, ready to be resumed
+    // or stopped
+    let picks_up = match request {
+        Request::Resume | Request::WorkOn { .. } | Request::Start { .. } => true,
+        Request::Stop => db::read_active_timer().await?.is_some(),
+        _ => false,
+    };
+    if picks_up {
+        ensure_running().await?;
+    }
+    match send(&request).await? {
+        Some(status) => println!("{}", status.summary()),
+        None => println!("No pomo is running"),
+    }
+    Ok(())
+}
@@ -1,15 +1,135 @@
-use crate::tasks::Task;
+use crate::{
+    args::{Filter, SortBy},
+    config,
+    export::{CycleRecord, TaskRecord},
+    states::AppResult,
+    tasks::{self, Task},
+};
 use chrono::Duration;
-use sqlx::types::chrono::{Local, NaiveDateTime};
-use sqlx::{query, query_as, Connection, Encode, FromRow, SqliteConnection};
-use std::env;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sqlx::query::QueryAs;
+use sqlx::sqlite::{SqliteArguments, SqliteConnectOptions};
+use sqlx::types::chrono::{Local, NaiveDate, NaiveDateTime};
+use sqlx::{
+    query, query_as, query_scalar, ConnectOptions, Connection, Encode, FromRow, Sqlite,
+    SqliteConnection,
+};
+use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
 
 #[derive(Debug, FromRow, Encode)]
 pub struct Cycle {
     pub id: i64,
-    pub task_id: i64,
+    pub task_id: Option<i64>,
+    pub session_id: Option<i64>,
+    /// `work`, `short_break` or `long_break`.
+    pub kind: String,
     pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub planned_secs: Option<i64>,
+    pub actual_secs: Option<i64>,
+    pub paused_secs: i64,
+    /// `None` while the cycle is still running.
+    pub outcome: Option<String>,
+}
+
+/// How a cycle ended.
+#[derive(Debug, Clone, Copy)]
+pub enum Outcome {
+    /// The timer ran out.
+    Completed,
+    /// The user moved on to the next cycle early.
+    Skipped,
+    /// The session was ended mid-cycle.
+    Abandoned,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Skipped => "skipped",
+            Self::Abandoned => "abandoned",
+        }
+    }
+}
+
+/// Something which broke the user's focus during a work cycle, as marked in
+/// the Pomodoro Technique.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Interruption {
+    /// The user's own urge to do something else, marked `'`.
+    Internal,
+    /// Someone or something else, marked `-`.
+    External,
+}
+
+impl Interruption {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Internal => "internal",
+            Self::External => "external",
+        }
+    }
+}
+
+/// Interruptions logged during a cycle.
+#[derive(Debug, Clone, Copy, Default, FromRow, Serialize, Deserialize)]
+pub struct Interruptions {
+    pub internal: u32,
+    pub external: u32,
+}
+
+/// Snapshot of the running pomo, kept up to date so it can be resumed after
+/// the app quits or crashes.
+#[derive(Debug, FromRow)]
+pub struct ActiveTimer {
+    pub task_id: Option<i64>,
+    pub session_id: i64,
+    pub phase: String,
+    pub started_at: NaiveDateTime,
+    pub dur_secs: i64,
+    pub paused_secs: i64,
+    pub paused_at: Option<NaiveDateTime>,
+    pub session_pomos: i64,
+    pub work_secs: i64,
+    pub short_break_secs: i64,
+    pub long_break_secs: i64,
+    pub long_break_interval: i64,
+    pub seen_at: NaiveDateTime,
+    pub cycle_id: Option<i64>,
+    pub waiting_since: Option<NaiveDateTime>,
+}
+
+/// Names of a task's tags, separated by spaces.
+const TASK_TAGS: &str = "(
+    SELECT GROUP_CONCAT(tags.name, ' ')
+    FROM task_tags JOIN tags ON tags.id = task_tags.tag_id
+    WHERE task_tags.task_id = tasks.id)";
+
+/// Pomos finished on all of a task's subtasks, however deeply nested.
+const SUBTASK_POMOS: &str = "(
+    WITH RECURSIVE subtasks(id, pomos_finished) AS (
+        SELECT sub.id, sub.pomos_finished FROM tasks AS sub WHERE sub.parent_id = tasks.id
+        UNION
+        SELECT sub.id, sub.pomos_finished
+        FROM tasks AS sub JOIN subtasks ON sub.parent_id = subtasks.id)
+    SELECT COALESCE(SUM(pomos_finished), 0) FROM subtasks)";
+
+/// Tasks along with their project, tags and subtasks' pomos, as
+/// `Task::from_row` expects.
+fn select_tasks(condition: &str) -> String {
+    format!(
+        "
+SELECT tasks.*, projects.name AS project, {} AS tags, {} AS subtask_pomos
+FROM tasks LEFT JOIN projects ON projects.id = tasks.project_id
+WHERE {}",
+        TASK_TAGS, SUBTASK_POMOS, condition
+    )
 }
 
 pub async fn get_conn() -> sqlx::Result<SqliteConnection> {
@@ -18,61 +138,548 @@ pub async fn get_conn() -> sqlx::Result<SqliteConnection> {
 
 pub async fn read_tasks() -> sqlx::Result<Vec<Task>> {
     let mut conn = get_conn().await?;
-    let vec = query_as("SELECT * FROM tasks").fetch_all(&mut conn).await?;
+    let vec = query_as(&select_tasks("archived IS NULL"))
+        .fetch_all(&mut conn)
+        .await?;
     Ok(vec)
 }
 
 pub async fn read_task(id: i64) -> sqlx::Result<Task> {
     let mut conn = get_conn().await?;
-    let vec = query_as(&format!("SELECT * FROM tasks WHERE id = {}", id))
+    let vec = query_as(&select_tasks("tasks.id = ?"))
+        .bind(id)
         .fetch_one(&mut conn)
         .await?;
     Ok(vec)
 }
 
-pub async fn print_tasks() -> sqlx::Result<()> {
-    let vec = read_tasks().await?;
-    vec.iter().for_each(|task| println!("{}", task.to_string()));
+/// The parent of the task with ID `id`, if it's still open but all of its
+/// other subtasks are completed or archived.
+pub async fn finished_parent(id: i64) -> sqlx::Result<Option<Task>> {
+    let mut conn = get_conn().await?;
+    query_as(&select_tasks(
+        "tasks.id = (SELECT parent_id FROM tasks WHERE id = ?)
+    AND tasks.completed IS NULL
+    AND NOT EXISTS (
+        SELECT 1 FROM tasks AS sub
+        WHERE sub.parent_id = tasks.id AND sub.completed IS NULL AND sub.archived IS NULL)",
+    ))
+    .bind(id)
+    .fetch_optional(&mut conn)
+    .await
+}
+
+/// Checks that the task with ID `id` (or a new task, if `None`) can be made a
+/// subtask of `parent_id`, without looping back on itself.
+async fn check_parent(
+    conn: &mut SqliteConnection,
+    id: Option<i64>,
+    parent_id: i64,
+) -> AppResult<()> {
+    let exists: bool = query_scalar("SELECT EXISTS(SELECT 1 FROM tasks WHERE id = ?)")
+        .bind(parent_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !exists {
+        return Err(format!("there's no task with ID {}", parent_id).into());
+    }
+    let Some(id) = id else {
+        return Ok(());
+    };
+    let looped: bool = query_scalar(
+        "
+WITH RECURSIVE ancestors(id) AS (
+    SELECT ?
+    UNION
+    SELECT tasks.parent_id FROM tasks JOIN ancestors ON tasks.id = ancestors.id
+    WHERE tasks.parent_id IS NOT NULL)
+SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?)",
+    )
+    .bind(parent_id)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if looped {
+        return Err("a task can't be a subtask of itself or of its own subtasks".into());
+    }
     Ok(())
 }
 
-pub async fn write_from_add(task: crate::args::Add) -> sqlx::Result<()> {
-    write_task(
+/// Prints the tasks with their subtasks indented underneath.
+pub async fn print_tasks(filter: &Filter, sort: Option<SortBy>) -> sqlx::Result<()> {
+    let mut vec = read_tasks().await?;
+    vec.retain(|task| task.matches(filter));
+    match sort {
+        Some(SortBy::Priority) => vec.sort_by(Task::cmp_priority),
+        Some(SortBy::Due) => vec.sort_by(Task::cmp_due),
+        None => {}
+    }
+    tasks::nest(vec)
+        .iter()
+        .for_each(|(depth, task)| println!("{}{}", "  ".repeat(*depth), task));
+    Ok(())
+}
+
+pub async fn write_from_add(task: crate::args::Add) -> AppResult<()> {
+    if let Some(parent) = task.parent {
+        check_parent(&mut get_conn().await?, None, parent).await?;
+    }
+    let defaults = &config::get().durations;
+    write_task(&Task {
+        desc: Some(task.desc),
+        work_secs: task.work_mins.unwrap_or(defaults.work_mins) * 60,
+        short_break_secs: task.short_break_mins.unwrap_or(defaults.short_break_mins) * 60,
+        long_break_secs: task.long_break_mins.unwrap_or(defaults.long_break_mins) * 60,
+        long_break_interval: task
+            .long_break_interval
+            .unwrap_or(defaults.long_break_interval),
+        project: task.project.as_deref().and_then(tasks::parse_project),
+        tags: tasks::parse_tags(task.tags.iter().map(String::as_str)),
+        priority: task.priority.unwrap_or_default(),
+        due: task.due,
+        estimated_pomos: task.estimate,
+        parent_id: task.parent.map(|parent| parent as u32),
+        ..Task::default()
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn write_from_edit(edit: crate::args::Edit) -> AppResult<()> {
+    let task = read_task(edit.id).await?;
+    if let Some(parent) = edit.parent {
+        check_parent(&mut get_conn().await?, Some(edit.id), parent).await?;
+    }
+    update_task(&Task {
+        desc: edit.desc.or(task.desc),
+        work_secs: edit.work.map_or(task.work_secs, |mins| mins * 60),
+        short_break_secs: edit.short.map_or(task.short_break_secs, |mins| mins * 60),
+        long_break_secs: edit.long.map_or(task.long_break_secs, |mins| mins * 60),
+        long_break_interval: edit.long_break_interval.unwrap_or(task.long_break_interval),
+        project: match edit.project {
+            Some(project) => tasks::parse_project(&project),
+            None => task.project,
+        },
+        tags: if edit.clear_tags {
+            Vec::new()
+        } else if edit.tags.is_empty() {
+            task.tags
+        } else {
+            tasks::parse_tags(edit.tags.iter().map(String::as_str))
+        },
+        priority: edit.priority.unwrap_or(task.priority),
+        due: if edit.no_due {
+            None
+        } else {
+            edit.due.or(task.due)
+        },
+        estimated_pomos: if edit.no_estimate {
+            None
+        } else {
+            edit.estimate.or(task.estimated_pomos)
+        },
+        parent_id: if edit.no_parent {
+            None
+        } else {
+            edit.parent.map(|parent| parent as u32).or(task.parent_id)
+        },
+        ..task
+    })
+    .await?;
+    Ok(())
+}
+
+/// Saves the description, durations, project, tags and parent of an existing
+/// task. Progress and completion are left alone.
+pub async fn update_task(task: &Task) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let mut tx = conn.begin().await?;
+    let id = task.id.unwrap();
+    let work_secs = task.work_secs as i64;
+    let short_break_secs = task.short_break_secs as i64;
+    let long_break_secs = task.long_break_secs as i64;
+    let priority = task.priority.level();
+    query!(
+        "UPDATE tasks SET desc = ?, work_secs = ?, short_break_secs = ?, long_break_secs = ?,
+        long_break_interval = ?, priority = ?, due = ?, estimated_pomos = ?, parent_id = ?
+        WHERE id = ?",
         task.desc,
-        task.work_mins as i64 * 60,
-        task.short_break_mins as i64 * 60,
-        task.long_break_mins as i64 * 60,
+        work_secs,
+        short_break_secs,
+        long_break_secs,
+        task.long_break_interval,
+        priority,
+        task.due,
+        task.estimated_pomos,
+        task.parent_id,
+        id
     )
-    .await
+    .execute(&mut *tx)
+    .await?;
+    save_labels(&mut tx, id as i64, task).await?;
+    tx.commit().await
 }
 
-pub async fn write_task(
-    desc: String,
-    work_secs: i64,
-    short_break_secs: i64,
-    long_break_secs: i64,
-) -> sqlx::Result<()> {
+/// Writes a new task, returning its ID.
+pub async fn write_task(task: &Task) -> sqlx::Result<i64> {
+    let mut conn = get_conn().await?;
+    let mut tx = conn.begin().await?;
+    let id = insert_task(&mut tx, task).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Writes all of `tasks` in a single transaction, so either all or none of
+/// them end up in the DB. Their own `id`s are only used to find their
+/// `parent_id`s among them: each subtask goes under whichever task was added in
+/// place of its parent, or at the top level if its parent isn't in `tasks`.
+pub async fn write_tasks(tasks: &[Task]) -> sqlx::Result<()> {
     let mut conn = get_conn().await?;
+    let mut tx = conn.begin().await?;
+    let mut ids = HashMap::new();
+    for task in tasks {
+        let orphan = Task {
+            parent_id: None,
+            ..task.clone()
+        };
+        let id = insert_task(&mut tx, &orphan).await?;
+        if let Some(old) = task.id {
+            ids.insert(old, id);
+        }
+    }
+    // parents may come after their subtasks, so they're linked once all are in
+    for task in tasks {
+        let id = task.id.and_then(|id| ids.get(&id));
+        let parent_id = task.parent_id.and_then(|id| ids.get(&id));
+        if let (Some(id), Some(parent_id)) = (id, parent_id) {
+            query!("UPDATE tasks SET parent_id = ? WHERE id = ?", parent_id, id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await
+}
+
+async fn insert_task(conn: &mut SqliteConnection, task: &Task) -> sqlx::Result<i64> {
+    let work_secs = task.work_secs as i64;
+    let short_break_secs = task.short_break_secs as i64;
+    let long_break_secs = task.long_break_secs as i64;
+    let priority = task.priority.level();
     // put task in DB
-    query!(
+    let id = query!(
         "
 INSERT INTO tasks 
-    (desc, work_secs, short_break_secs, long_break_secs, pomos_finished) 
-VALUES (?, ?, ?, ?, 0)
+    (desc, work_secs, short_break_secs, long_break_secs, long_break_interval,
+     pomos_finished, completed, priority, due, estimated_pomos, parent_id) 
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ",
-        desc,
+        task.desc,
         work_secs,
         short_break_secs,
         long_break_secs,
+        task.long_break_interval,
+        task.pomos_finished,
+        task.completed,
+        priority,
+        task.due,
+        task.estimated_pomos,
+        task.parent_id,
+    )
+    .execute(&mut *conn)
+    .await?
+    .last_insert_rowid();
+    save_labels(conn, id, task).await?;
+    Ok(id)
+}
+
+/// Points the task with ID `id` at `task`'s project and tags, creating any
+/// which don't exist yet.
+async fn save_labels(conn: &mut SqliteConnection, id: i64, task: &Task) -> sqlx::Result<()> {
+    let project_id = match &task.project {
+        Some(name) => {
+            query!("INSERT OR IGNORE INTO projects (name) VALUES (?)", name)
+                .execute(&mut *conn)
+                .await?;
+            let project = query!("SELECT id FROM projects WHERE name = ?", name)
+                .fetch_one(&mut *conn)
+                .await?;
+            Some(project.id)
+        }
+        None => None,
+    };
+    query!(
+        "UPDATE tasks SET project_id = ? WHERE id = ?",
+        project_id,
+        id
+    )
+    .execute(&mut *conn)
+    .await?;
+    query!("DELETE FROM task_tags WHERE task_id = ?", id)
+        .execute(&mut *conn)
+        .await?;
+    for tag in &task.tags {
+        query!("INSERT OR IGNORE INTO tags (name) VALUES (?)", tag)
+            .execute(&mut *conn)
+            .await?;
+        query!(
+            "INSERT INTO task_tags (task_id, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+            id,
+            tag
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Records the start of a cycle, returning its ID.
+pub async fn start_cycle(
+    task_id: Option<i64>,
+    session_id: i64,
+    kind: &str,
+    started_at: NaiveDateTime,
+    planned_secs: i64,
+) -> sqlx::Result<i64> {
+    let mut conn = get_conn().await?;
+    let id = query!(
+        "
+INSERT INTO cycles
+    (task_id, session_id, kind, started_at, planned_secs)
+VALUES (?, ?, ?, ?, ?)
+        ",
+        task_id,
+        session_id,
+        kind,
+        started_at,
+        planned_secs,
+    )
+    .execute(&mut conn)
+    .await?
+    .last_insert_rowid();
+    Ok(id)
+}
+
+pub async fn finish_cycle(
+    id: i64,
+    outcome: Outcome,
+    actual_secs: i64,
+    paused_secs: i64,
+) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let now = Local::now().naive_local();
+    let outcome = outcome.as_str();
+    query!(
+        "
+UPDATE cycles
+SET ended_at = ?, outcome = ?, actual_secs = ?, paused_secs = ?
+WHERE id = ?
+        ",
+        now,
+        outcome,
+        actual_secs,
+        paused_secs,
+        id,
+    )
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+/// Saves what the user wrote about a cycle, or clears it if `None`.
+pub async fn set_cycle_note(id: i64, note: Option<&str>) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!("UPDATE cycles SET note = ? WHERE id = ?", note, id)
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// A finished work cycle, as listed in the cycle log.
+#[derive(Debug, FromRow)]
+pub struct LoggedCycle {
+    pub id: i64,
+    pub task_desc: Option<String>,
+    pub ended_at: NaiveDateTime,
+    pub actual_secs: Option<i64>,
+    pub outcome: String,
+    pub note: Option<String>,
+}
+
+/// The `limit` most recently finished work cycles, newest first.
+pub async fn read_cycle_log(limit: i64) -> sqlx::Result<Vec<LoggedCycle>> {
+    let mut conn = get_conn().await?;
+    query_as(
+        "
+SELECT cycles.id, tasks.desc AS task_desc, cycles.ended_at, cycles.actual_secs,
+       cycles.outcome, cycles.note
+FROM cycles LEFT JOIN tasks ON tasks.id = cycles.task_id
+WHERE cycles.kind = 'work' AND cycles.outcome IS NOT NULL
+ORDER BY cycles.ended_at DESC, cycles.id DESC
+LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(&mut conn)
+    .await
+}
+
+/// A completed task, as listed in the history.
+#[derive(Debug, FromRow)]
+pub struct CompletedTask {
+    #[sqlx(flatten)]
+    pub task: Task,
+    /// Time spent in work cycles on the task and all of its subtasks.
+    pub focus_secs: i64,
+}
+
+/// Completed tasks which haven't been archived, most recently completed first,
+/// skipping the first `offset`.
+pub async fn read_history(limit: i64, offset: i64) -> sqlx::Result<Vec<CompletedTask>> {
+    let mut conn = get_conn().await?;
+    let sql = format!(
+        "
+SELECT history.*, (
+    WITH RECURSIVE family(id) AS (
+        SELECT history.id
+        UNION
+        SELECT sub.id FROM tasks AS sub JOIN family ON sub.parent_id = family.id)
+    SELECT COALESCE(SUM(cycles.actual_secs), 0) FROM cycles
+    WHERE cycles.kind = 'work' AND cycles.task_id IN (SELECT id FROM family)) AS focus_secs
+FROM ({}) AS history
+ORDER BY history.completed DESC, history.id DESC
+LIMIT ? OFFSET ?",
+        select_tasks("tasks.completed IS NOT NULL AND tasks.archived IS NULL")
+    );
+    query_as(&sql)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut conn)
+        .await
+}
+
+/// How many tasks `read_history` can page through, by the day they were
+/// completed.
+pub async fn count_history() -> sqlx::Result<HashMap<NaiveDate, i64>> {
+    let mut conn = get_conn().await?;
+    let counts: Vec<(NaiveDate, i64)> = query_as(
+        "
+SELECT DATE(completed) AS day, COUNT(*) FROM tasks
+WHERE completed IS NOT NULL AND archived IS NULL
+GROUP BY day",
+    )
+    .fetch_all(&mut conn)
+    .await?;
+    Ok(counts.into_iter().collect())
+}
+
+pub async fn extend_cycle(id: i64, secs: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!(
+        "UPDATE cycles SET extended_secs = extended_secs + ? WHERE id = ?",
+        secs,
+        id
+    )
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+/// Records how long the user took to start the cycle after this one.
+pub async fn acknowledge_cycle(id: i64, ack_secs: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!("UPDATE cycles SET ack_secs = ? WHERE id = ?", ack_secs, id)
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Marks a finished cycle as running again.
+pub async fn reopen_cycle(id: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!(
+        "UPDATE cycles SET ended_at = NULL, outcome = NULL, actual_secs = NULL WHERE id = ?",
+        id
+    )
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_cycle(id: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let mut tx = conn.begin().await?;
+    query!("DELETE FROM interruptions WHERE cycle_id = ?", id)
+        .execute(&mut *tx)
+        .await?;
+    query!("DELETE FROM cycles WHERE id = ?", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+pub async fn log_interruption(
+    cycle_id: i64,
+    kind: Interruption,
+    reason: Option<&str>,
+) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let now = Local::now().naive_local();
+    let kind = kind.as_str();
+    query!(
+        "INSERT INTO interruptions (cycle_id, kind, reason, logged_at) VALUES (?, ?, ?, ?)",
+        cycle_id,
+        kind,
+        reason,
+        now
+    )
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn count_interruptions(cycle_id: i64) -> sqlx::Result<Interruptions> {
+    let mut conn = get_conn().await?;
+    query_as(
+        "
+SELECT COALESCE(SUM(kind = 'internal'), 0) AS internal,
+       COALESCE(SUM(kind = 'external'), 0) AS external
+FROM interruptions WHERE cycle_id = ?",
+    )
+    .bind(cycle_id)
+    .fetch_one(&mut conn)
+    .await
+}
+
+/// Opens a new session for the given task, returning its ID.
+pub async fn start_session(task_id: Option<i64>) -> sqlx::Result<i64> {
+    let mut conn = get_conn().await?;
+    let now = Local::now().naive_local();
+    let id = query!(
+        "INSERT INTO sessions (task_id, started_at) VALUES (?, ?)",
+        task_id,
+        now
+    )
+    .execute(&mut conn)
+    .await?
+    .last_insert_rowid();
+    Ok(id)
+}
+
+pub async fn set_session_finished(id: i64, finished: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!(
+        "UPDATE sessions SET pomos_finished = ? WHERE id = ?",
+        finished,
+        id
     )
     .execute(&mut conn)
     .await?;
     Ok(())
 }
 
-pub async fn complete_cycle(task_id: Option<i64>) -> sqlx::Result<()> {
+pub async fn end_session(id: i64) -> sqlx::Result<()> {
     let mut conn = get_conn().await?;
-    query!("INSERT INTO cycles (task_id) VALUES (?)", task_id)
+    let now = Local::now().naive_local();
+    query!("UPDATE sessions SET ended_at = ? WHERE id = ?", now, id)
         .execute(&mut conn)
         .await?;
     Ok(())
@@ -84,7 +691,9 @@ async fn num_in_day(day: NaiveDateTime) -> sqlx::Result<usize> {
     let result = query!(
         r#"SELECT COUNT(*) as count
            FROM cycles
-           WHERE DATE(created_at) = ?"#,
+           WHERE DATE(ended_at) = ?
+             AND kind = 'work'
+             AND outcome IN ('completed', 'skipped')"#,
         date_str
     )
     .fetch_one(&mut conn)
@@ -121,20 +730,255 @@ pub async fn last_n_day_cycles(n: usize) -> sqlx::Result<Vec<(NaiveDateTime, usi
     .await
 }
 
-pub async fn write_and_return_task(
-    desc: String,
-    work_secs: i64,
-    short_break_secs: i64,
-    long_break_secs: i64,
-) -> Result<Task, sqlx::Error> {
-    write_task(desc, work_secs, short_break_secs, long_break_secs).await?;
+/// Finished work cycles which ended within a date range, on tasks matching a
+/// `Filter`; the parameters are bound by `bind_range`.
+const WORK_BETWEEN: &str = "
+    cycles.kind = 'work'
+    AND cycles.outcome IS NOT NULL
+    AND DATE(cycles.ended_at) BETWEEN ? AND ?
+    AND (? IS NULL OR cycles.task_id IN (
+        SELECT tasks.id FROM tasks JOIN projects ON projects.id = tasks.project_id
+        WHERE projects.name = ?))
+    AND (? IS NULL OR cycles.task_id IN (
+        SELECT task_tags.task_id FROM task_tags JOIN tags ON tags.id = task_tags.tag_id
+        WHERE tags.name = ?))";
+
+type SqliteQueryAs<'q, O> = QueryAs<'q, Sqlite, O, SqliteArguments<'q>>;
+
+fn bind_range<'q, O>(
+    query: SqliteQueryAs<'q, O>,
+    from: NaiveDate,
+    to: NaiveDate,
+    filter: &'q Filter,
+) -> SqliteQueryAs<'q, O> {
+    query
+        .bind(from)
+        .bind(to)
+        .bind(&filter.project)
+        .bind(&filter.project)
+        .bind(&filter.tag)
+        .bind(&filter.tag)
+}
+
+/// Pomos and focus time over some span of cycles.
+#[derive(Debug, Default, FromRow, Serialize)]
+pub struct Totals {
+    /// Work cycles which weren't abandoned, i.e. those counted as pomos.
+    pub pomos: i64,
+    /// Work cycles of any outcome.
+    pub cycles: i64,
+    pub completed: i64,
+    pub focus_secs: i64,
+    /// Net time added to work cycles while they ran.
+    pub extended_secs: i64,
+    pub internal_interruptions: i64,
+    pub external_interruptions: i64,
+}
+
+const TOTALS_COLUMNS: &str = "
+    COALESCE(SUM(cycles.outcome IN ('completed', 'skipped')), 0) AS pomos,
+    COUNT(*) AS cycles,
+    COALESCE(SUM(cycles.outcome = 'completed'), 0) AS completed,
+    COALESCE(SUM(cycles.actual_secs), 0) AS focus_secs,
+    COALESCE(SUM(cycles.extended_secs), 0) AS extended_secs,
+    COALESCE(SUM((SELECT COUNT(*) FROM interruptions
+        WHERE interruptions.cycle_id = cycles.id AND interruptions.kind = 'internal')), 0)
+        AS internal_interruptions,
+    COALESCE(SUM((SELECT COUNT(*) FROM interruptions
+        WHERE interruptions.cycle_id = cycles.id AND interruptions.kind = 'external')), 0)
+        AS external_interruptions";
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct PeriodTotals {
+    pub period: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct ProjectTotals {
+    pub project: Option<String>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct TaskTotals {
+    pub task_id: Option<i64>,
+    pub desc: Option<String>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+pub async fn totals(from: NaiveDate, to: NaiveDate, filter: &Filter) -> sqlx::Result<Totals> {
     let mut conn = get_conn().await?;
-    // extract newly created task from db
-    query_as("SELECT * FROM tasks ORDER BY rowid DESC")
+    let sql = format!(
+        "SELECT {} FROM cycles WHERE {}",
+        TOTALS_COLUMNS, WORK_BETWEEN
+    );
+    bind_range(query_as(&sql), from, to, filter)
         .fetch_one(&mut conn)
         .await
 }
 
+/// Totals grouped by `strftime(period_format, ended_at)`, e.g. `%Y-%m` for months.
+pub async fn totals_by_period(
+    period_format: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    filter: &Filter,
+) -> sqlx::Result<Vec<PeriodTotals>> {
+    let mut conn = get_conn().await?;
+    let sql = format!(
+        "
+SELECT strftime(?, cycles.ended_at) AS period, {}
+FROM cycles
+WHERE {}
+GROUP BY period
+ORDER BY period",
+        TOTALS_COLUMNS, WORK_BETWEEN
+    );
+    bind_range(query_as(&sql).bind(period_format), from, to, filter)
+        .fetch_all(&mut conn)
+        .await
+}
+
+pub async fn totals_by_project(
+    from: NaiveDate,
+    to: NaiveDate,
+    filter: &Filter,
+) -> sqlx::Result<Vec<ProjectTotals>> {
+    let mut conn = get_conn().await?;
+    let sql = format!(
+        "
+SELECT projects.name AS project, {}
+FROM cycles
+    LEFT JOIN tasks ON tasks.id = cycles.task_id
+    LEFT JOIN projects ON projects.id = tasks.project_id
+WHERE {}
+GROUP BY projects.id
+ORDER BY pomos DESC, focus_secs DESC",
+        TOTALS_COLUMNS, WORK_BETWEEN
+    );
+    bind_range(query_as(&sql), from, to, filter)
+        .fetch_all(&mut conn)
+        .await
+}
+
+pub async fn totals_by_task(
+    from: NaiveDate,
+    to: NaiveDate,
+    filter: &Filter,
+) -> sqlx::Result<Vec<TaskTotals>> {
+    let mut conn = get_conn().await?;
+    let sql = format!(
+        "
+SELECT cycles.task_id, tasks.desc, {}
+FROM cycles LEFT JOIN tasks ON tasks.id = cycles.task_id
+WHERE {}
+GROUP BY cycles.task_id
+ORDER BY pomos DESC, focus_secs DESC",
+        TOTALS_COLUMNS, WORK_BETWEEN
+    );
+    bind_range(query_as(&sql), from, to, filter)
+        .fetch_all(&mut conn)
+        .await
+}
+
+/// How completed tasks with an estimate measured up to it.
+#[derive(Debug, Default, FromRow, Serialize)]
+pub struct Estimates {
+    pub tasks: i64,
+    /// Tasks finished in no more pomos than estimated.
+    pub within_estimate: i64,
+    pub estimated_pomos: i64,
+    pub actual_pomos: i64,
+    /// Average number of pomos each task was off by, either way.
+    pub mean_error: f64,
+}
+
+/// Estimates for tasks completed within a date range and matching a `Filter`.
+pub async fn estimates(from: NaiveDate, to: NaiveDate, filter: &Filter) -> sqlx::Result<Estimates> {
+    let mut conn = get_conn().await?;
+    // a parent's pomos include its subtasks'
+    query_as(&format!(
+        "
+SELECT COUNT(*) AS tasks,
+    COALESCE(SUM(actual <= estimated_pomos), 0) AS within_estimate,
+    COALESCE(SUM(estimated_pomos), 0) AS estimated_pomos,
+    COALESCE(SUM(actual), 0) AS actual_pomos,
+    COALESCE(AVG(ABS(actual - estimated_pomos)), 0.0) AS mean_error
+FROM (SELECT tasks.*, pomos_finished + {} AS actual FROM tasks)
+WHERE estimated_pomos IS NOT NULL
+    AND completed IS NOT NULL
+    AND DATE(completed) BETWEEN ? AND ?
+    AND (? IS NULL OR project_id IN (SELECT id FROM projects WHERE name = ?))
+    AND (? IS NULL OR id IN (
+        SELECT task_tags.task_id FROM task_tags JOIN tags ON tags.id = task_tags.tag_id
+        WHERE tags.name = ?))",
+        SUBTASK_POMOS
+    ))
+    .bind(from)
+    .bind(to)
+    .bind(&filter.project)
+    .bind(&filter.project)
+    .bind(&filter.tag)
+    .bind(&filter.tag)
+    .fetch_one(&mut conn)
+    .await
+}
+
+/// Tasks which are incomplete, or were completed on or after `since`.
+pub async fn export_tasks(since: NaiveDate) -> sqlx::Result<Vec<TaskRecord>> {
+    let mut conn = get_conn().await?;
+    query_as(&format!(
+        "
+SELECT tasks.id, desc, work_secs, short_break_secs, long_break_secs, long_break_interval,
+       pomos_finished, completed, archived, projects.name AS project, {} AS tags,
+       CASE priority WHEN 3 THEN 'high' WHEN 2 THEN 'medium' WHEN 1 THEN 'low' END AS priority,
+       due, estimated_pomos, parent_id
+FROM tasks LEFT JOIN projects ON projects.id = tasks.project_id
+WHERE completed IS NULL OR DATE(completed) >= ?
+ORDER BY tasks.id",
+        TASK_TAGS
+    ))
+    .bind(since)
+    .fetch_all(&mut conn)
+    .await
+}
+
+/// Finished cycles which ended on or after `since`.
+pub async fn export_cycles(since: NaiveDate) -> sqlx::Result<Vec<CycleRecord>> {
+    let mut conn = get_conn().await?;
+    query_as(
+        "
+SELECT cycles.id, cycles.task_id, tasks.desc AS task_desc, cycles.session_id, cycles.kind,
+       cycles.started_at, cycles.ended_at, cycles.planned_secs, cycles.actual_secs,
+       cycles.paused_secs, cycles.outcome, cycles.extended_secs, cycles.ack_secs,
+       cycles.note,
+       (SELECT COUNT(*) FROM interruptions
+        WHERE interruptions.cycle_id = cycles.id AND interruptions.kind = 'internal')
+           AS internal_interruptions,
+       (SELECT COUNT(*) FROM interruptions
+        WHERE interruptions.cycle_id = cycles.id AND interruptions.kind = 'external')
+           AS external_interruptions
+FROM cycles LEFT JOIN tasks ON tasks.id = cycles.task_id
+WHERE cycles.outcome IS NOT NULL AND DATE(cycles.ended_at) >= ?
+ORDER BY cycles.id",
+    )
+    .bind(since)
+    .fetch_all(&mut conn)
+    .await
+}
+
+pub async fn write_and_return_task(task: &Task) -> Result<Task, sqlx::Error> {
+    let id = write_task(task).await?;
+    read_task(id).await
+}
+
 pub async fn set_finished(id: i64, finished: i64) -> Result<(), sqlx::Error> {
     let mut conn = get_conn().await?;
     query!(
@@ -147,24 +991,216 @@ pub async fn set_finished(id: i64, finished: i64) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Clears a task's completion, putting it back in the New or In Progress list.
+pub async fn reopen(id: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let result = query!("UPDATE tasks SET completed = NULL WHERE id = ?", id)
+        .execute(&mut conn)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+pub async fn archive(id: i64) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    let now = Local::now().naive_local();
+    let result = query!("UPDATE tasks SET archived = ? WHERE id = ?", now, id)
+        .execute(&mut conn)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    Ok(())
+}
+
+/// Deletes a task along with its sessions. Its cycles are either deleted too,
+/// or kept with no task so they still count towards stats.
+///
+/// Refuses while the task has an unfinished pomo, which still refers to it.
+pub async fn delete_task(id: i64, keep_cycles: bool) -> AppResult<()> {
+    let mut conn = get_conn().await?;
+    let mut tx = conn.begin().await?;
+    let active: bool = query_scalar("SELECT EXISTS(SELECT 1 FROM active_timer WHERE task_id = ?)")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if active {
+        return Err("task has an unfinished pomo, stop it first with `pogodoro stop`".into());
+    }
+    if keep_cycles {
+        query!("UPDATE cycles SET task_id = NULL WHERE task_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+        query!("UPDATE sessions SET task_id = NULL WHERE task_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+    } else {
+        query!(
+            "DELETE FROM interruptions WHERE cycle_id IN (SELECT id FROM cycles WHERE task_id = ?)",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        query!("DELETE FROM cycles WHERE task_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+        query!("DELETE FROM sessions WHERE task_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    query!("DELETE FROM task_tags WHERE task_id = ?", id)
+        .execute(&mut *tx)
+        .await?;
+    // its subtasks move up a level rather than going with it
+    query!(
+        "UPDATE tasks SET parent_id = (SELECT parent_id FROM tasks WHERE id = ?)
+        WHERE parent_id = ?",
+        id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let result = query!("DELETE FROM tasks WHERE id = ?", id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn complete(id: i64) -> sqlx::Result<()> {
     let mut conn = get_conn().await?;
-    let now = Local::now();
+    // in local time, so that DATE() gives the day it was completed here
+    let now = Local::now().naive_local();
     query!("UPDATE tasks SET completed = ? WHERE rowid = ?", now, id)
         .execute(&mut conn)
         .await?;
     Ok(())
 }
 
+pub async fn save_active_timer(timer: &ActiveTimer) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!(
+        "
+INSERT OR REPLACE INTO active_timer
+    (id, task_id, session_id, phase, started_at, dur_secs, paused_secs, paused_at,
+     session_pomos, work_secs, short_break_secs, long_break_secs, long_break_interval,
+     seen_at, cycle_id, waiting_since)
+VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ",
+        timer.task_id,
+        timer.session_id,
+        timer.phase,
+        timer.started_at,
+        timer.dur_secs,
+        timer.paused_secs,
+        timer.paused_at,
+        timer.session_pomos,
+        timer.work_secs,
+        timer.short_break_secs,
+        timer.long_break_secs,
+        timer.long_break_interval,
+        timer.seen_at,
+        timer.cycle_id,
+        timer.waiting_since,
+    )
+    .execute(&mut conn)
+    .await?;
+    Ok(())
+}
+
+/// Records that the running pomo was still going at `at`.
+pub async fn touch_active_timer(at: NaiveDateTime) -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!("UPDATE active_timer SET seen_at = ? WHERE id = 1", at)
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn read_active_timer() -> sqlx::Result<Option<ActiveTimer>> {
+    let mut conn = get_conn().await?;
+    query_as("SELECT * FROM active_timer WHERE id = 1")
+        .fetch_optional(&mut conn)
+        .await
+}
+
+pub async fn clear_active_timer() -> sqlx::Result<()> {
+    let mut conn = get_conn().await?;
+    query!("DELETE FROM active_timer")
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
 pub fn path() -> PathBuf {
-    let mut path = env::var("HOME").unwrap();
-    path.push_str("/.config/pogodoro/records.db");
-    path.into()
+    config::get().database.path.clone()
 }
 
 pub async fn setup() -> Result<(), sqlx::Error> {
     let path = path();
-    let mut conn = SqliteConnection::connect(path.to_str().unwrap()).await?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .connect()
+        .await?;
     sqlx::migrate!().run(&mut conn).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh in-memory DB holding tasks 1 <- 2 <- 3 (3 is a subtask of 2,
+    /// and 2 of 1) and a separate task 4.
+    async fn tree() -> SqliteConnection {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!().run(&mut conn).await.unwrap();
+        for parent_id in [None, Some(1), Some(2), None] {
+            let task = Task {
+                parent_id,
+                ..Task::default()
+            };
+            insert_task(&mut conn, &task).await.unwrap();
+        }
+        conn
+    }
+
+    #[tokio::test]
+    async fn tasks_can_move_anywhere_but_under_themselves() {
+        let mut conn = tree().await;
+        assert!(check_parent(&mut conn, None, 3).await.is_ok());
+        assert!(check_parent(&mut conn, Some(4), 3).await.is_ok());
+        assert!(check_parent(&mut conn, Some(3), 1).await.is_ok());
+        assert!(check_parent(&mut conn, Some(2), 4).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn loops_are_refused() {
+        let mut conn = tree().await;
+        for (id, parent_id) in [(1, 1), (1, 2), (1, 3), (2, 3)] {
+            let err = check_parent(&mut conn, Some(id), parent_id)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "a task can't be a subtask of itself or of its own subtasks"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn parents_must_exist() {
+        let mut conn = tree().await;
+        let err = check_parent(&mut conn, None, 5).await.unwrap_err();
+        assert_eq!(err.to_string(), "there's no task with ID 5");
+    }
+}
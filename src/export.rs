@@ -0,0 +1,104 @@
+use crate::{
+    args::{self, Format},
+    db,
+    states::AppResult,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+// The records below are the schema of `pogodoro export`. Scripts read these
+// by name and position, so fields should only ever be appended.
+
+/// A row of `pogodoro export --tasks`.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub id: i64,
+    pub desc: Option<String>,
+    pub work_secs: i64,
+    pub short_break_secs: i64,
+    pub long_break_secs: i64,
+    pub long_break_interval: i64,
+    pub pomos_finished: i64,
+    pub completed: Option<NaiveDateTime>,
+    pub archived: Option<NaiveDateTime>,
+    pub project: Option<String>,
+    /// Separated by spaces.
+    pub tags: Option<String>,
+    /// `high`, `medium` or `low`.
+    pub priority: Option<String>,
+    pub due: Option<NaiveDate>,
+    pub estimated_pomos: Option<i64>,
+    /// ID of the task this is a subtask of.
+    pub parent_id: Option<i64>,
+}
+
+/// A row of `pogodoro export --cycles`. Only finished cycles are exported.
+#[derive(Debug, FromRow, Serialize)]
+pub struct CycleRecord {
+    pub id: i64,
+    pub task_id: Option<i64>,
+    pub task_desc: Option<String>,
+    pub session_id: Option<i64>,
+    /// `work`, `short_break` or `long_break`.
+    pub kind: String,
+    pub started_at: Option<NaiveDateTime>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub planned_secs: Option<i64>,
+    pub actual_secs: Option<i64>,
+    pub paused_secs: i64,
+    /// `completed`, `skipped` or `abandoned`.
+    pub outcome: Option<String>,
+    pub extended_secs: i64,
+    pub ack_secs: Option<i64>,
+    /// What the user wrote down once a work cycle was over.
+    pub note: Option<String>,
+    pub internal_interruptions: i64,
+    pub external_interruptions: i64,
+}
+
+pub async fn export(args: args::Export) -> AppResult<()> {
+    // dates are compared as text in SQLite, so keep this to a four-digit year
+    let since = args
+        .since
+        .unwrap_or(NaiveDate::from_ymd_opt(1, 1, 1).unwrap());
+    if args.tasks {
+        let records = db::export_tasks(since).await?;
+        write_records(&records, args.format, open_output(&args)?)
+    } else {
+        let records = db::export_cycles(since).await?;
+        write_records(&records, args.format, open_output(&args)?)
+    }
+}
+
+fn open_output(args: &args::Export) -> io::Result<Box<dyn Write>> {
+    Ok(match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+fn write_records<T: Serialize>(
+    records: &[T],
+    format: Format,
+    mut out: impl Write,
+) -> AppResult<()> {
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, records)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use crate::{
+    db::{self, CompletedTask},
+    pomodoro::centered_rect,
+    states::{AppResult, State},
+    stats::format_secs,
+    tasks::{Priority, Task, TasksState},
+};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::{collections::HashMap, io};
+use tui::{
+    layout::Constraint,
+    prelude::{Alignment, CrosstermBackend},
+    style::{Color, Modifier, Style},
+    text::Text,
+    widgets::{
+        block::Title, Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Table, TableState,
+    },
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// How many completed tasks are shown on each page.
+const PAGE_SIZE: i64 = 50;
+const HELP_TEXT: &str = "[j] / [k] - move down / up
+[]] / [[] - next / previous page
+[i] / [enter] - inspect the selected task
+[r] - reopen the selected task
+[q] / [esc] - return to tasks page";
+
+/// Pages through every completed task, grouped by the day it was completed.
+pub struct HistoryState {
+    tasks: Vec<CompletedTask>,
+    /// Completed tasks across all pages.
+    total: i64,
+    /// Completed tasks on each day, counting those on other pages too.
+    per_day: HashMap<NaiveDate, i64>,
+    page: i64,
+    /// Index into `tasks`, which the table's selection is worked out from.
+    selected: Option<usize>,
+    state: TableState,
+    inspecting: bool,
+    show_help: bool,
+}
+
+#[async_trait]
+impl State for HistoryState {
+    async fn tick(&mut self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn should_finish(&self) -> bool {
+        false
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
+        let area = frame.size();
+        let header = Row::new(["Task", "Completed", "Pomos", "Focus"].map(|h| {
+            Cell::from(Text::styled(
+                h,
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC),
+            ))
+        }))
+        .bottom_margin(1);
+        let day_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+
+        let mut rows = Vec::new();
+        let mut selected_row = None;
+        let mut day = None;
+        for (i, completed) in self.tasks.iter().enumerate() {
+            let task = &completed.task;
+            let at = task.completed.unwrap();
+            if day != Some(at.date()) {
+                day = Some(at.date());
+                let count = self.per_day.get(&at.date()).copied().unwrap_or_default();
+                rows.push(
+                    Row::new([Cell::from(format!(
+                        "{} ({} task{})",
+                        at.format("%a %d/%m/%Y"),
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    ))])
+                    .style(day_style),
+                );
+            }
+            if self.selected == Some(i) {
+                selected_row = Some(rows.len());
+            }
+            rows.push(Row::new([
+                Cell::from(format!(
+                    "  {}{}",
+                    task.desc.as_deref().unwrap_or_default(),
+                    task.labels()
+                )),
+                Cell::from(at.format("%H:%M").to_string()),
+                Cell::from(task.progress()).style(if task.is_over_estimate() {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                }),
+                Cell::from(format_secs(completed.focus_secs)),
+            ]));
+        }
+        self.state.select(selected_row);
+
+        let pages = (self.total.max(1) + PAGE_SIZE - 1) / PAGE_SIZE;
+        let table = Table::new(rows)
+            .header(header)
+            .block(
+                Block::default()
+                    .title(
+                        Title::from(format!(
+                            "History, page {}/{} ({} completed) [?] help",
+                            self.page + 1,
+                            pages,
+                            self.total
+                        ))
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red))
+            .widths(&[
+                Constraint::Percentage(70),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Length(8),
+            ]);
+        frame.render_stateful_widget(table, area, &mut self.state);
+
+        if self.inspecting {
+            if let Some(completed) = self.selected.map(|i| &self.tasks[i]) {
+                let text = details(completed);
+                let width = text.lines().map(|line| line.width()).max().unwrap_or(0) + 2;
+                let chunk = centered_rect(
+                    (width as u16).min(area.width),
+                    text.lines().count() as u16 + 2,
+                    area,
+                );
+                let details = Paragraph::new(text)
+                    .block(
+                        Block::default()
+                            .title("Task details")
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .style(Style::default().fg(Color::Yellow));
+                frame.render_widget(Clear, chunk);
+                frame.render_widget(details, chunk);
+            }
+        } else if self.show_help {
+            let help_chunk = centered_rect(46, 7, area);
+            let help_text = Paragraph::new(HELP_TEXT)
+                .block(
+                    Block::default()
+                        .title("Help")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(Clear, help_chunk);
+            frame.render_widget(help_text, help_chunk);
+        }
+    }
+
+    async fn handle_key_event(mut self: Box<Self>, event: KeyEvent) -> AppResult<Box<dyn State>> {
+        if self.inspecting {
+            if matches!(
+                event.code,
+                KeyCode::Char('i' | 'q') | KeyCode::Enter | KeyCode::Esc
+            ) {
+                self.inspecting = false;
+            }
+            return Ok(self);
+        }
+        match event.code {
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.move_selection(self.tasks.len().saturating_sub(1))
+            }
+            KeyCode::PageDown | KeyCode::Char(']') if (self.page + 1) * PAGE_SIZE < self.total => {
+                return Ok(Box::new(Self::load(self.page + 1).await?))
+            }
+            KeyCode::PageUp | KeyCode::Char('[') if self.page > 0 => {
+                return Ok(Box::new(Self::load(self.page - 1).await?))
+            }
+            KeyCode::Char('i') | KeyCode::Enter => self.inspecting = self.selected.is_some(),
+            KeyCode::Char('r') => {
+                if let Some(i) = self.selected {
+                    db::reopen(self.tasks[i].task.id.unwrap() as i64).await?;
+                    let mut history = Self::load(self.page).await?;
+                    // stay about where the reopened task was
+                    history.selected = history.selected.map(|_| i.min(history.tasks.len() - 1));
+                    return Ok(Box::new(history));
+                }
+            }
+            KeyCode::Char('?') => self.show_help = !self.show_help,
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Box::new(TasksState::new().await?)),
+            _ => {}
+        }
+        Ok(self)
+    }
+}
+
+impl HistoryState {
+    pub async fn new() -> sqlx::Result<Self> {
+        Self::load(0).await
+    }
+
+    /// Reads page `page` (from 0), or the last page if there are fewer.
+    async fn load(page: i64) -> sqlx::Result<Self> {
+        let per_day = db::count_history().await?;
+        let total = per_day.values().sum::<i64>();
+        let page = page.min((total - 1).max(0) / PAGE_SIZE);
+        let tasks = db::read_history(PAGE_SIZE, page * PAGE_SIZE).await?;
+        Ok(Self {
+            selected: (!tasks.is_empty()).then_some(0),
+            tasks,
+            total,
+            per_day,
+            page,
+            state: TableState::default(),
+            inspecting: false,
+            show_help: false,
+        })
+    }
+
+    /// Moves the selection `by` tasks down, wrapping around.
+    fn move_selection(&mut self, by: usize) {
+        if self.tasks.is_empty() {
+            return;
+        }
+        self.selected = Some(self.selected.map_or(0, |i| (i + by) % self.tasks.len()));
+    }
+}
+
+/// Everything worth knowing about a completed task, one fact per line.
+fn details(completed: &CompletedTask) -> String {
+    let task = &completed.task;
+    let mut lines = vec![
+        format!("ID:         {}", task.id.unwrap()),
+        format!(
+            "Task:       {}{}",
+            task.desc.as_deref().unwrap_or_default(),
+            task.labels()
+        ),
+    ];
+    if task.priority != Priority::None {
+        lines.push(format!("Priority:   {}", task.priority.as_str()));
+    }
+    if let Some(due) = task.due {
+        lines.push(format!("Due:        {}", due));
+    }
+    if let Some(parent_id) = task.parent_id {
+        lines.push(format!("Subtask of: {}", parent_id));
+    }
+    lines.extend([
+        format!(
+            "Completed:  {}",
+            task.completed.unwrap().format("%Y-%m-%d %H:%M")
+        ),
+        format!(
+            "Pomos:      {}{}",
+            task.progress(),
+            if task.is_over_estimate() {
+                ", over estimate"
+            } else {
+                ""
+            }
+        ),
+        format!("Focused:    {}", format_secs(completed.focus_secs)),
+        format!(
+            "Durations:  {}/{}/{} x{}",
+            Task::format_time(task.work_secs),
+            Task::format_time(task.short_break_secs),
+            Task::format_time(task.long_break_secs),
+            task.long_break_interval
+        ),
+    ]);
+    lines.join("\n")
+}
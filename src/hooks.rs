@@ -0,0 +1,117 @@
+use crate::{config, db::Outcome, pomodoro::PomodoroState, tasks::Task};
+use log::{info, warn};
+use std::{process::Stdio, time::Duration};
+use tokio::{process::Command, task::JoinHandle, time};
+
+/// Something that happened to the pomo, which users can hook commands onto.
+#[derive(Clone, Copy, Debug)]
+pub enum Event {
+    WorkStart,
+    WorkEnd(Outcome),
+    BreakStart,
+    BreakEnd(Outcome),
+    Pause,
+    Resume,
+    TaskComplete,
+}
+
+impl Event {
+    pub fn start_of(phase: PomodoroState) -> Self {
+        match phase {
+            PomodoroState::Work => Self::WorkStart,
+            _ => Self::BreakStart,
+        }
+    }
+
+    pub fn end_of(phase: PomodoroState, outcome: Outcome) -> Self {
+        match phase {
+            PomodoroState::Work => Self::WorkEnd(outcome),
+            _ => Self::BreakEnd(outcome),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::WorkStart => "work_start",
+            Self::WorkEnd(_) => "work_end",
+            Self::BreakStart => "break_start",
+            Self::BreakEnd(_) => "break_end",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::TaskComplete => "task_complete",
+        }
+    }
+
+    fn command(&self) -> Option<&'static str> {
+        let hooks = &config::get().hooks;
+        match self {
+            Self::WorkStart => &hooks.work_start,
+            Self::WorkEnd(_) => &hooks.work_end,
+            Self::BreakStart => &hooks.break_start,
+            Self::BreakEnd(_) => &hooks.break_end,
+            Self::Pause => &hooks.pause,
+            Self::Resume => &hooks.resume,
+            Self::TaskComplete => &hooks.task_complete,
+        }
+        .as_deref()
+    }
+}
+
+/// A configured command, ready to run with details of the event.
+pub struct Hook {
+    event: Event,
+    command: Command,
+}
+
+/// Runs the command configured for `event` in the background, if there is one.
+///
+/// Failures and timeouts are logged rather than returned, so a broken hook
+/// never gets in the way of the timer. The handle only needs awaiting by
+/// callers about to exit.
+pub fn run(event: Event, task: &Task, phase: Option<PomodoroState>) -> Option<JoinHandle<()>> {
+    Some(spawn([prepare(event, task, phase)?]))
+}
+
+pub fn prepare(event: Event, task: &Task, phase: Option<PomodoroState>) -> Option<Hook> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(event.command()?)
+        .env("POGO_EVENT", event.as_str())
+        .env(
+            "POGO_TASK_ID",
+            task.id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .env("POGO_TASK_DESC", task.desc.as_deref().unwrap_or_default())
+        .env("POGO_PHASE", phase.map_or("", |phase| phase.as_str()))
+        .env("POGO_POMOS_FINISHED", task.pomos_finished.to_string())
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    if let Event::WorkEnd(outcome) | Event::BreakEnd(outcome) = event {
+        command.env("POGO_OUTCOME", outcome.as_str());
+    }
+    Some(Hook { event, command })
+}
+
+/// Runs `hooks` one after the other in the background, so that e.g. a
+/// `work_end` hook is done before the following `break_start` one begins.
+pub fn spawn(hooks: impl IntoIterator<Item = Hook>) -> JoinHandle<()> {
+    let hooks: Vec<Hook> = hooks.into_iter().collect();
+    let timeout = Duration::from_secs(config::get().hooks.timeout_secs);
+    tokio::spawn(async move {
+        for Hook { event, mut command } in hooks {
+            let name = event.as_str();
+            match time::timeout(timeout, command.output()).await {
+                Ok(Ok(output)) if output.status.success() => info!("{} hook finished", name),
+                Ok(Ok(output)) => warn!(
+                    "{} hook failed with {}: {}",
+                    name,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Ok(Err(err)) => warn!("couldn't run {} hook: {}", name, err),
+                Err(_) => warn!("{} hook killed after {}s", name, timeout.as_secs()),
+            }
+        }
+    })
+}
@@ -0,0 +1,466 @@
+use crate::{
+    args::{self, ImportFormat},
+    db,
+    states::AppResult,
+    tasks::{self, Priority, Task},
+};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+/// A task to import, shaped like a row of `pogodoro export --tasks`.
+///
+/// Only `desc` is required: missing durations fall back to the config, and
+/// any unknown field is ignored.
+#[derive(Deserialize)]
+struct ImportRecord {
+    /// Only used to find the subtasks' parents, as `parent_id` refers to it.
+    id: Option<u32>,
+    parent_id: Option<u32>,
+    desc: Option<String>,
+    work_secs: Option<u64>,
+    short_break_secs: Option<u64>,
+    long_break_secs: Option<u64>,
+    long_break_interval: Option<u32>,
+    pomos_finished: Option<u32>,
+    completed: Option<NaiveDateTime>,
+    project: Option<String>,
+    /// Separated by spaces or commas.
+    tags: Option<String>,
+    /// `high`, `medium` or `low`.
+    priority: Option<String>,
+    due: Option<NaiveDate>,
+    estimated_pomos: Option<u32>,
+}
+
+pub async fn import(args: args::Import) -> AppResult<()> {
+    let format = match args.format {
+        Some(format) => format,
+        None => guess_format(&args.file)
+            .ok_or("couldn't tell the file's format from its extension, try passing --format")?,
+    };
+    let text = fs::read_to_string(&args.file)?;
+    let parsed = match format {
+        ImportFormat::Csv => parse_csv(&text),
+        ImportFormat::Json => parse_json(&text),
+        ImportFormat::Todo => parse_todo(&text),
+    };
+    // report every bad row at once, rather than making the user fix them one at a time
+    let mut errors: Vec<String> = parsed.iter().filter_map(|r| r.clone().err()).collect();
+    if errors.is_empty() {
+        let tasks: Vec<Task> = parsed.iter().cloned().map(Result::unwrap).collect();
+        errors = check_parents(&tasks);
+    }
+    if !errors.is_empty() {
+        return Err(format!(
+            "nothing imported from {}:\n  {}",
+            args.file.display(),
+            errors.join("\n  ")
+        )
+        .into());
+    }
+
+    let mut seen: HashSet<String> = db::read_tasks()
+        .await?
+        .iter()
+        .filter_map(|task| task.desc.as_deref().map(dedup_key))
+        .collect();
+    let (new, duplicates): (Vec<Task>, Vec<Task>) = parsed
+        .into_iter()
+        .map(Result::unwrap)
+        .partition(|task| seen.insert(dedup_key(task.desc.as_deref().unwrap())));
+
+    if args.dry_run {
+        println!("would import {} tasks:", new.len());
+        new.iter().for_each(|task| println!("  {}", summary(task)));
+        if !duplicates.is_empty() {
+            println!("would skip {} duplicates:", duplicates.len());
+            duplicates
+                .iter()
+                .for_each(|task| println!("  {}", summary(task)));
+        }
+        return Ok(());
+    }
+
+    db::write_tasks(&new).await?;
+    println!(
+        "imported {} tasks, skipped {} duplicates",
+        new.len(),
+        duplicates.len()
+    );
+    Ok(())
+}
+
+fn guess_format(path: &Path) -> Option<ImportFormat> {
+    match path.extension()?.to_str()? {
+        "csv" => Some(ImportFormat::Csv),
+        "json" => Some(ImportFormat::Json),
+        "txt" | "todo" => Some(ImportFormat::Todo),
+        _ => None,
+    }
+}
+
+/// Descriptions are compared ignoring case and surrounding whitespace.
+fn dedup_key(desc: &str) -> String {
+    desc.trim().to_lowercase()
+}
+
+fn summary(task: &Task) -> String {
+    format!(
+        "{}{}{}{}{} ({}/{}/{} x{})",
+        task.priority
+            .marker()
+            .map_or(String::new(), |marker| format!("{} ", marker)),
+        task.desc.as_deref().unwrap(),
+        task.labels(),
+        task.due
+            .map_or(String::new(), |due| format!(" due:{}", due)),
+        task.estimated_pomos
+            .map_or(String::new(), |pomos| format!(" est:{}", pomos)),
+        Task::format_time(task.work_secs),
+        Task::format_time(task.short_break_secs),
+        Task::format_time(task.long_break_secs),
+        task.long_break_interval
+    )
+}
+
+fn parse_csv(text: &str) -> Vec<Result<Task, String>> {
+    csv::Reader::from_reader(text.as_bytes())
+        .deserialize()
+        .enumerate()
+        // row 1 is the header
+        .map(|(i, record)| {
+            let row = i + 2;
+            record
+                .map_err(|err| format!("row {}: {}", row, err))
+                .and_then(|record| to_task(record).map_err(|err| format!("row {}: {}", row, err)))
+        })
+        .collect()
+}
+
+fn parse_json(text: &str) -> Vec<Result<Task, String>> {
+    match serde_json::from_str::<Vec<ImportRecord>>(text) {
+        Ok(records) => records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| to_task(record).map_err(|err| format!("entry {}: {}", i + 1, err)))
+            .collect(),
+        Err(err) => vec![Err(err.to_string())],
+    }
+}
+
+fn to_task(record: ImportRecord) -> Result<Task, String> {
+    let desc = match record.desc {
+        Some(desc) if !desc.trim().is_empty() => desc.trim().to_string(),
+        _ => return Err("missing desc".into()),
+    };
+    let defaults = Task::default();
+    let task = Task {
+        id: record.id,
+        parent_id: record.parent_id,
+        desc: Some(desc),
+        work_secs: record.work_secs.unwrap_or(defaults.work_secs),
+        short_break_secs: record.short_break_secs.unwrap_or(defaults.short_break_secs),
+        long_break_secs: record.long_break_secs.unwrap_or(defaults.long_break_secs),
+        long_break_interval: record
+            .long_break_interval
+            .unwrap_or(defaults.long_break_interval),
+        pomos_finished: record.pomos_finished.unwrap_or(0),
+        completed: record.completed,
+        project: record.project.as_deref().and_then(tasks::parse_project),
+        tags: tasks::parse_tags(record.tags.as_deref()),
+        priority: match record.priority {
+            Some(text) => Priority::parse(&text).ok_or(format!("unknown priority '{}'", text))?,
+            None => Priority::None,
+        },
+        due: record.due,
+        estimated_pomos: record.estimated_pomos.filter(|&pomos| pomos > 0),
+        ..defaults
+    };
+    validate(&task)?;
+    Ok(task)
+}
+
+/// Checks that the IDs the tasks refer to each other by are unique, and that
+/// following their `parent_id`s never loops back, e.g. in a hand-edited file.
+fn check_parents(tasks: &[Task]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut parents = HashMap::new();
+    for task in tasks {
+        let Some(id) = task.id else { continue };
+        if parents.insert(id, task.parent_id).is_some() {
+            errors.push(format!("ID {} is used by more than one task", id));
+        }
+    }
+    for &start in parents.keys() {
+        let mut id = start;
+        // a chain can't be longer than the number of tasks without looping
+        for _ in 0..parents.len() {
+            match parents.get(&id).copied().flatten() {
+                Some(parent) if parent == start => {
+                    errors.push(format!(
+                        "task {} can't be a subtask of itself or of its own subtasks",
+                        start
+                    ));
+                    break;
+                }
+                Some(parent) => id = parent,
+                None => break,
+            }
+        }
+    }
+    errors.sort();
+    errors.dedup();
+    errors
+}
+
+fn validate(task: &Task) -> Result<(), String> {
+    for (name, secs) in [
+        ("work", task.work_secs),
+        ("short break", task.short_break_secs),
+        ("long break", task.long_break_secs),
+    ] {
+        if secs == 0 {
+            return Err(format!("{} duration must be more than zero", name));
+        }
+    }
+    if task.long_break_interval == 0 {
+        return Err("long_break_interval must be at least 1".into());
+    }
+    Ok(())
+}
+
+/// Parses todo.txt lines, e.g. `x 2023-09-14 (A) write report +work work:50 short:10`.
+///
+/// Completion markers, priorities and dates are understood, but only the
+/// completion and priority are kept: `(A)` is high priority, `(B)` medium
+/// and anything lower is low. Durations can be set per line in minutes with
+/// the `work:`, `short:` and `long:` extensions, the long break interval with
+/// `set:`, the due date with `due:YYYY-MM-DD` and the estimated pomos with
+/// `est:`. The first `+project` becomes the task's project and each
+/// `@context` one of its tags. Everything else, including any other
+/// `+project`, is kept in the description.
+fn parse_todo(text: &str) -> Vec<Result<Task, String>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_todo_line(line).map_err(|err| format!("line {}: {}", i + 1, err)))
+        .collect()
+}
+
+fn parse_todo_line(line: &str) -> Result<Task, String> {
+    let mut words = line.split_whitespace().peekable();
+    let mut completed = None;
+    if words.peek() == Some(&"x") {
+        words.next();
+        completed = Some(Local::now().naive_local());
+        if let Some(date) = words.peek().and_then(|w| parse_date(w)) {
+            completed = date.and_hms_opt(0, 0, 0);
+            words.next();
+        }
+    }
+    let mut priority = Priority::None;
+    if let Some(word) = words.next_if(|w| is_priority(w)) {
+        priority = match word.as_bytes()[1] {
+            b'A' => Priority::High,
+            b'B' => Priority::Medium,
+            _ => Priority::Low,
+        };
+    }
+    // creation date
+    if words.peek().and_then(|w| parse_date(w)).is_some() {
+        words.next();
+    }
+
+    let mut task = Task {
+        completed,
+        priority,
+        ..Task::default()
+    };
+    let mut desc = Vec::new();
+    let mut contexts = Vec::new();
+    for word in words {
+        let parse_mins = |value: &str| {
+            value
+                .parse::<f64>()
+                .map(|mins| (mins * 60.0) as u64)
+                .map_err(|_| format!("invalid number of minutes in '{}'", word))
+        };
+        match word.split_once(':') {
+            Some(("work", mins)) => task.work_secs = parse_mins(mins)?,
+            Some(("short", mins)) => task.short_break_secs = parse_mins(mins)?,
+            Some(("long", mins)) => task.long_break_secs = parse_mins(mins)?,
+            Some(("due", date)) => {
+                task.due = Some(parse_date(date).ok_or(format!("invalid due date in '{}'", word))?)
+            }
+            Some(("est", pomos)) => {
+                task.estimated_pomos = Some(
+                    pomos
+                        .parse()
+                        .ok()
+                        .filter(|&pomos| pomos > 0)
+                        .ok_or(format!("invalid number of pomos in '{}'", word))?,
+                )
+            }
+            Some(("set", pomos)) => {
+                task.long_break_interval = pomos
+                    .parse()
+                    .map_err(|_| format!("invalid number of pomos in '{}'", word))?
+            }
+            _ if word.len() > 1 && word.starts_with('@') => contexts.push(word),
+            _ if word.len() > 1 && word.starts_with('+') && task.project.is_none() => {
+                task.project = tasks::parse_project(word)
+            }
+            _ => desc.push(word),
+        }
+    }
+    task.tags = tasks::parse_tags(contexts);
+    if desc.is_empty() {
+        return Err("missing description".into());
+    }
+    task.desc = Some(desc.join(" "));
+    validate(&task)?;
+    Ok(task)
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+}
+
+fn is_priority(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 3 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todo_line_reads_priority_labels_and_extensions() {
+        let task = parse_todo_line(
+            "(A) 2023-09-01 write report +work +other @desk @Email due:2023-09-20 est:3 work:50 short:10 long:20 set:3",
+        )
+        .unwrap();
+        assert_eq!(task.desc.as_deref(), Some("write report +other"));
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.project.as_deref(), Some("work"));
+        assert_eq!(task.tags, ["desk", "Email"]);
+        assert_eq!(task.due, parse_date("2023-09-20"));
+        assert_eq!(task.estimated_pomos, Some(3));
+        assert_eq!(
+            (task.work_secs, task.short_break_secs, task.long_break_secs),
+            (3000, 600, 1200)
+        );
+        assert_eq!(task.long_break_interval, 3);
+        assert_eq!(task.completed, None);
+    }
+
+    #[test]
+    fn todo_line_uses_defaults_for_missing_durations() {
+        let task = parse_todo_line("water plants").unwrap();
+        let defaults = Task::default();
+        assert_eq!(task.desc.as_deref(), Some("water plants"));
+        assert_eq!(task.priority, Priority::None);
+        assert_eq!(task.work_secs, defaults.work_secs);
+        assert_eq!(task.long_break_interval, defaults.long_break_interval);
+    }
+
+    #[test]
+    fn todo_line_reads_completion_date() {
+        let task = parse_todo_line("x 2023-09-14 2023-09-01 file taxes").unwrap();
+        assert_eq!(
+            task.completed,
+            parse_date("2023-09-14").unwrap().and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(task.desc.as_deref(), Some("file taxes"));
+    }
+
+    #[test]
+    fn todo_priorities_below_b_are_low() {
+        assert_eq!(parse_todo_line("(B) a").unwrap().priority, Priority::Medium);
+        assert_eq!(parse_todo_line("(D) a").unwrap().priority, Priority::Low);
+        // not a priority unless it's a single capital letter
+        assert_eq!(
+            parse_todo_line("(a) a").unwrap().desc.as_deref(),
+            Some("(a) a")
+        );
+    }
+
+    #[test]
+    fn todo_line_rejects_bad_values() {
+        assert!(parse_todo_line("x 2023-09-14").is_err());
+        assert!(parse_todo_line("task work:abc").is_err());
+        assert!(parse_todo_line("task work:0").is_err());
+        assert!(parse_todo_line("task due:tomorrow").is_err());
+        assert!(parse_todo_line("task est:0").is_err());
+        assert!(parse_todo_line("task set:0").is_err());
+    }
+
+    #[test]
+    fn todo_errors_give_line_numbers_and_skip_blank_lines() {
+        let parsed = parse_todo("first\n\n   \nsecond work:x\n");
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].is_ok());
+        assert!(parsed[1].as_ref().unwrap_err().starts_with("line 4: "));
+    }
+
+    fn subtask(id: u32, parent_id: Option<u32>) -> Task {
+        Task {
+            id: Some(id),
+            parent_id,
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn json_keeps_ids_to_link_subtasks() {
+        let parsed = parse_json(
+            r#"[{"id": 7, "desc": "parent"}, {"id": 9, "parent_id": 7, "desc": "child"}]"#,
+        );
+        let tasks: Vec<Task> = parsed.into_iter().map(Result::unwrap).collect();
+        assert_eq!((tasks[0].id, tasks[0].parent_id), (Some(7), None));
+        assert_eq!((tasks[1].id, tasks[1].parent_id), (Some(9), Some(7)));
+    }
+
+    #[test]
+    fn parents_may_be_missing_or_come_later() {
+        let tasks = [
+            subtask(1, Some(2)),
+            subtask(2, None),
+            subtask(3, Some(40)),
+            Task::default(),
+        ];
+        assert!(check_parents(&tasks).is_empty());
+    }
+
+    #[test]
+    fn parents_looping_back_are_reported() {
+        let tasks = [
+            subtask(1, Some(1)),
+            subtask(2, Some(3)),
+            subtask(3, Some(2)),
+        ];
+        assert_eq!(
+            check_parents(&tasks),
+            [
+                "task 1 can't be a subtask of itself or of its own subtasks",
+                "task 2 can't be a subtask of itself or of its own subtasks",
+                "task 3 can't be a subtask of itself or of its own subtasks",
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_ids_are_reported() {
+        let tasks = [subtask(1, None), subtask(1, None)];
+        assert_eq!(
+            check_parents(&tasks),
+            ["ID 1 is used by more than one task"]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-/// Application.
+//! Application.
 
 /// Terminal events handler.
 pub mod event;
@@ -7,7 +7,19 @@ pub mod event;
 pub mod tui;
 
 pub mod args;
+pub mod config;
+pub mod cycle_log;
+pub mod daemon;
 pub mod db;
+pub mod export;
+pub mod history;
+pub mod hooks;
+pub mod import;
+pub mod notify;
 pub mod pomodoro;
+pub mod resume;
 pub mod states;
+pub mod stats;
+pub mod status;
 pub mod tasks;
+pub mod timer_view;
@@ -2,53 +2,70 @@ use clap::Parser;
 use flexi_logger::{FileSpec, Logger};
 use pogodoro::{
     args::Cli,
+    config::{self, Config},
     db,
     event::{Event, EventHandler},
     states::{parse_args, AppResult},
     tui::Tui,
 };
-use std::io;
+use std::{io, process};
 use tui::{backend::CrosstermBackend, Terminal};
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
     // Read command line args
     let args = Cli::parse();
+    let config = Config::load(args.config.as_deref()).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1)
+    });
+    config::init(config);
+    db::setup().await?;
     // Create an application.
-    let state = parse_args(args.command).await?;
-    if state.is_none() {
-        return Ok(());
-    }
-    let mut state = state.unwrap();
+    let mut state = match parse_args(args.command).await {
+        Ok(Some(state)) => state,
+        Ok(None) => return Ok(()),
+        // subcommands which don't open the UI report errors to the user directly
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1)
+        }
+    };
 
     Logger::try_with_env()?
         .log_to_file(FileSpec::default())
         .print_message()
         .start()?;
-    db::setup().await?;
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
-    let events = EventHandler::new(250);
+    let events = EventHandler::new(config::get().ui.tick_rate_ms);
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
-    // Start the main loop.
-    loop {
-        if state.should_finish() {
-            break;
+    // Start the main loop, keeping hold of any error until the terminal is restored.
+    let result: AppResult<()> = async {
+        loop {
+            if state.should_finish() {
+                return Ok(());
+            }
+            // Render the user interface.
+            tui.draw(&mut state)?;
+            // Handle events.
+            match tui.events.next()? {
+                Event::Tick => state.tick().await?,
+                Event::Key(key_event) => state = state.handle_key_event(key_event).await?,
+                _ => {}
+            };
         }
-        // Render the user interface.
-        tui.draw(&mut state)?;
-        // Handle events.
-        match tui.events.next()? {
-            Event::Tick => state.tick().await?,
-            Event::Key(key_event) => state = state.handle_key_event(key_event).await?,
-            _ => {}
-        };
     }
+    .await;
     // Exit the user interface.
     tui.exit()?;
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1)
+    }
     Ok(())
 }
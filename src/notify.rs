@@ -0,0 +1,223 @@
+use crate::{
+    config::{self, Backend},
+    daemon::{self, Request},
+    pomodoro::{Pomodoro, PomodoroState},
+    states::AppResult,
+};
+use async_trait::async_trait;
+use log::{error, info, warn};
+use notify_rust::Notification;
+use std::{fs::OpenOptions, io::Write, process::Stdio, time::Duration};
+use tokio::{process::Command, runtime::Handle, sync::oneshot, task, time};
+
+const TITLE: &str = "pogodoro";
+/// How long the `command` backend gets before it counts as failed.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A way of telling the user the pomo has moved on to its next phase.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, title: &str, body: &str) -> AppResult<()>;
+}
+
+pub struct Desktop {
+    /// Offer to snooze for this long, if not `0`.
+    pub snooze_mins: u64,
+}
+
+#[async_trait]
+impl Notifier for Desktop {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    async fn notify(&self, title: &str, body: &str) -> AppResult<()> {
+        let mut notification = Notification::new();
+        notification.summary(title).body(body);
+        if self.snooze_mins == 0 {
+            // showing it talks to the notification server synchronously
+            task::spawn_blocking(move || notification.show().map(drop).map_err(|e| e.to_string()))
+                .await??;
+            return Ok(());
+        }
+        let label = format!("Snooze {}m", self.snooze_mins);
+        notification.action("snooze", &label);
+        let secs = self.snooze_mins * 60;
+        // waiting for the action blocks until the notification is closed, so
+        // only the outcome of showing it is reported back
+        let (shown, result) = oneshot::channel();
+        let runtime = Handle::current();
+        task::spawn_blocking(move || {
+            let handle = match notification.show() {
+                Ok(handle) => {
+                    let _ = shown.send(Ok(()));
+                    handle
+                }
+                Err(err) => {
+                    let _ = shown.send(Err(err.to_string()));
+                    return;
+                }
+            };
+            handle.wait_for_action(|action| {
+                if action == "snooze" {
+                    if let Err(err) = runtime.block_on(daemon::send(&Request::Snooze { secs })) {
+                        error!("couldn't snooze: {}", err);
+                    }
+                }
+            });
+        });
+        result.await??;
+        Ok(())
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    async fn notify(&self, title: &str, body: &str) -> AppResult<()> {
+        Notification::new().summary(title).body(body).show()?;
+        Ok(())
+    }
+}
+
+pub struct Bell;
+
+#[async_trait]
+impl Notifier for Bell {
+    async fn notify(&self, _title: &str, _body: &str) -> AppResult<()> {
+        OpenOptions::new()
+            .write(true)
+            .open("/dev/tty")?
+            .write_all(b"\x07")?;
+        Ok(())
+    }
+}
+
+pub struct External(pub String);
+
+#[async_trait]
+impl Notifier for External {
+    async fn notify(&self, title: &str, body: &str) -> AppResult<()> {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.0)
+            .env("POGO_TITLE", title)
+            .env("POGO_BODY", body)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = time::timeout(COMMAND_TIMEOUT, output)
+            .await
+            .map_err(|_| "timed out")??;
+        if !output.status.success() {
+            return Err(format!(
+                "exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+pub struct Log;
+
+#[async_trait]
+impl Notifier for Log {
+    async fn notify(&self, title: &str, body: &str) -> AppResult<()> {
+        info!("{}: {}", title, body);
+        Ok(())
+    }
+}
+
+pub struct Silent;
+
+#[async_trait]
+impl Notifier for Silent {
+    async fn notify(&self, _title: &str, _body: &str) -> AppResult<()> {
+        Ok(())
+    }
+}
+
+fn notifier(backend: Backend) -> Box<dyn Notifier> {
+    match backend {
+        Backend::Desktop => Box::new(Desktop {
+            snooze_mins: config::get().notifications.snooze_mins,
+        }),
+        Backend::Bell => Box::new(Bell),
+        // checked when the config is loaded
+        Backend::Command => Box::new(External(
+            config::get().notifications.command.clone().unwrap(),
+        )),
+        Backend::Log => Box::new(Log),
+        Backend::None => Box::new(Silent),
+    }
+}
+
+/// Announces the phase `pomodoro` has just started. Failures are only logged,
+/// since a missed notification shouldn't stop the timer.
+pub fn phase_started(pomodoro: &Pomodoro) {
+    let config = &config::get().notifications;
+    if !config.enabled {
+        return;
+    }
+    let template = match pomodoro.state {
+        PomodoroState::Work => &config.work,
+        PomodoroState::ShortBreak => &config.short_break,
+        PomodoroState::LongBreak => &config.long_break,
+    };
+    send(render(template, pomodoro))
+}
+
+/// Announces that the current phase is over and the next is waiting on the
+/// user, with `timer.manual_advance`.
+pub fn phase_over(pomodoro: &Pomodoro) {
+    let config = &config::get().notifications;
+    if config.enabled {
+        send(render(&config.waiting, pomodoro))
+    }
+}
+
+/// Warns that the task has just gone over its estimated pomos.
+pub fn over_estimate(pomodoro: &Pomodoro) {
+    let config = &config::get().notifications;
+    if config.enabled {
+        send(render(&config.over_estimate, pomodoro))
+    }
+}
+
+/// Tries each configured backend in turn until one of them works, in the
+/// background like hooks, so a slow backend doesn't hold up the timer.
+fn send(body: String) {
+    tokio::spawn(async move {
+        let config = &config::get().notifications;
+        for &backend in &config.backends {
+            match notifier(backend).notify(TITLE, &body).await {
+                Ok(()) => return,
+                Err(err) => warn!("{:?} notification failed: {}", backend, err),
+            }
+        }
+        warn!("no notification backend worked for: {}", body);
+    });
+}
+
+fn render(template: &str, pomodoro: &Pomodoro) -> String {
+    template
+        .replace(
+            "{task}",
+            pomodoro.task.desc.as_deref().unwrap_or("your session"),
+        )
+        .replace("{phase}", &pomodoro.state.to_string())
+        .replace("{next}", &pomodoro.next_state().to_string().to_lowercase())
+        .replace("{pomos}", &pomodoro.task.total_pomos().to_string())
+        .replace(
+            "{estimate}",
+            &pomodoro
+                .task
+                .estimated_pomos
+                .map_or("?".into(), |estimate| estimate.to_string()),
+        )
+        .replace(
+            "{set}",
+            &format!(
+                "{}/{}",
+                pomodoro.set_position(),
+                pomodoro.task.long_break_interval
+            ),
+        )
+}
@@ -1,82 +1,122 @@
 use crate::{
-    db,
-    states::{AppResult, State},
-    tasks::{Task, TasksState},
-};
-use async_trait::async_trait;
-use crossterm::event::{KeyCode, KeyEvent};
-use notify_rust::Notification;
-use std::{
-    cmp::max,
-    fmt, io,
-    time::{Duration, Instant},
+    config,
+    db::{self, Interruption, Interruptions, Outcome},
+    hooks::{self, Event},
+    notify,
+    states::AppResult,
+    tasks::Task,
 };
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::{fmt, mem, str::FromStr, time::Duration};
 use tui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    prelude::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, BorderType, Borders, Gauge, Paragraph},
-    Frame,
 };
-use unicode_width::UnicodeWidthStr;
+
+/// How often a running pomo records that it's still going. At most this much
+/// of a cycle is lost if the app dies.
+const HEARTBEAT_SECS: i64 = 10;
 
 #[derive(Debug)]
 pub struct Timer {
-    start_time: Instant,
+    /// Wall-clock start time, so a timer can outlive the process running it.
+    started_at: NaiveDateTime,
     dur: Duration,
     elapsed: Duration,
-    paused: bool,
+    /// Total time spent paused, not counting a pause still in progress.
+    paused_for: Duration,
+    paused_at: Option<NaiveDateTime>,
 }
 
 impl Timer {
     fn new(dur: Duration) -> Self {
         Self {
             dur,
-            start_time: Instant::now(),
+            started_at: now(),
             elapsed: Duration::from_secs(0),
-            paused: false,
+            paused_for: Duration::from_secs(0),
+            paused_at: None,
         }
     }
 
-    fn update(&mut self) {
-        if !self.paused {
-            self.elapsed = self.start_time.elapsed();
-        }
+    pub fn update(&mut self) {
+        let until = self.paused_at.unwrap_or_else(now);
+        self.elapsed = (until - self.started_at)
+            .to_std()
+            .unwrap_or_default()
+            .saturating_sub(self.paused_for);
     }
 
     pub fn is_finished(&self) -> bool {
         self.elapsed >= self.dur
     }
 
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.dur
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Moves the end of the timer by `secs`, but never to before now.
+    /// Returns how far it actually moved.
+    pub fn extend(&mut self, secs: i64) -> i64 {
+        self.update();
+        let before = self.dur;
+        let by = Duration::from_secs(secs.unsigned_abs());
+        self.dur = if secs >= 0 {
+            self.dur + by
+        } else {
+            self.dur.saturating_sub(by).max(self.elapsed)
+        };
+        self.dur.as_secs() as i64 - before.as_secs() as i64
+    }
+
     pub fn toggle_pause(&mut self) {
-        if !self.paused {
-            self.paused = true;
-            return;
+        match self.paused_at.take() {
+            Some(paused_at) => {
+                self.paused_for += (now() - paused_at).to_std().unwrap_or_default();
+            }
+            None => self.paused_at = Some(now()),
         }
-        self.paused = false;
-        self.start_time = Instant::now() - self.elapsed;
         self.update()
     }
 }
 
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
 impl fmt::Display for Timer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.is_finished() {
             write!(f, "Finished!")
         } else {
-            let to_go = (self.dur - self.elapsed + Duration::from_secs(1)).as_secs();
-            let mins = to_go / 60;
-            let hours = mins / 60;
-            if hours > 0 {
-                write!(f, "{}h{}m{}s", hours, mins - 60 * hours, to_go % 60)
-            } else {
-                write!(f, "{}m{}s", mins, to_go % 60)
-            }
+            let to_go = self.dur - self.elapsed + Duration::from_secs(1);
+            write!(f, "{}", format_remaining(to_go.as_secs()))
         }
     }
 }
 
-#[derive(Debug)]
+/// Formats time left on a timer, e.g. `12m5s` or `1h2m5s`.
+pub fn format_remaining(secs: u64) -> String {
+    let mins = secs / 60;
+    let hours = mins / 60;
+    if hours > 0 {
+        format!("{}h{}m{}s", hours, mins - 60 * hours, secs % 60)
+    } else {
+        format!("{}m{}s", mins, secs % 60)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PomodoroState {
     Work,
     ShortBreak,
@@ -84,17 +124,33 @@ pub enum PomodoroState {
 }
 
 impl PomodoroState {
-    fn notify(&self) -> AppResult<()> {
-        let message = match self {
-            Self::Work => "Time to work!",
-            Self::ShortBreak => "Short break time!",
-            Self::LongBreak => "Long break time!",
-        };
-        Notification::new()
-            .summary("pogodoro")
-            .body(message)
-            .show()?;
-        Ok(())
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Work => "work",
+            Self::ShortBreak => "short_break",
+            Self::LongBreak => "long_break",
+        }
+    }
+
+    pub fn style(&self) -> Style {
+        match self {
+            Self::Work => Style::default().fg(Color::Red),
+            Self::ShortBreak => Style::default().fg(Color::Green),
+            Self::LongBreak => Style::default().fg(Color::Blue),
+        }
+    }
+}
+
+impl FromStr for PomodoroState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "work" => Ok(Self::Work),
+            "short_break" => Ok(Self::ShortBreak),
+            "long_break" => Ok(Self::LongBreak),
+            _ => Err(format!("unknown pomo phase: {}", s)),
+        }
     }
 }
 
@@ -118,194 +174,466 @@ pub struct Pomodoro {
     pub current: Timer,
     pub task: Task,
     pub state: PomodoroState,
-    pub show_help: bool,
-    pub should_finish: bool,
+    /// Session opened when this pomo started; it drives break scheduling.
+    pub session_id: i64,
+    /// Work cycles finished in this session, as opposed to the task's lifetime count.
+    pub session_pomos: u32,
+    /// Row in `cycles` recording the current phase.
+    pub cycle_id: i64,
+    /// The phase which ended to start the current one, kept so it can be snoozed.
+    previous: Option<Phase>,
+    /// When the current cycle finished, if it's waiting on the user to start
+    /// the next one.
+    waiting_since: Option<NaiveDateTime>,
+    /// Last reminder that the next cycle is waiting, if any.
+    reminded_at: Option<NaiveDateTime>,
+    /// Logged during the current cycle.
+    pub interruptions: Interruptions,
+    /// When the pomo was last recorded as still going.
+    seen_at: NaiveDateTime,
 }
 
-const POMO_HEIGHT: u16 = 5;
-const POMO_WIDTH: u16 = 25;
-const HELP_TEXT: &str = "[p] - toggle pause on current pomo
-[n] - skip to next cycle in pomo
-[q] - quit session and return to command line
-[enter] - complete task and return to tasks page
-[?] - toggle this help page";
-
-impl Default for Pomodoro {
-    fn default() -> Self {
-        let task = Task::default();
-        let mut first_timer = Timer::new(Duration::from_secs(task.work_secs));
-        first_timer.update();
-        Self {
+/// A finished phase of a pomo.
+#[derive(Debug)]
+struct Phase {
+    state: PomodoroState,
+    timer: Timer,
+    cycle_id: i64,
+}
+
+impl Pomodoro {
+    /// Starts a new session working on `task`.
+    pub async fn new(task: Task) -> sqlx::Result<Self> {
+        let session_id = db::start_session(task.id.map(|i| i as i64)).await?;
+        let mut current = Timer::new(Duration::from_secs(task.work_secs));
+        current.update();
+        let mut pomodoro = Self {
             id: None,
-            current: first_timer,
-            task: Task::default(),
+            current,
+            task,
             state: PomodoroState::Work,
-            show_help: false,
-            should_finish: false,
-        }
+            session_id,
+            session_pomos: 0,
+            cycle_id: 0,
+            previous: None,
+            waiting_since: None,
+            reminded_at: None,
+            interruptions: Interruptions::default(),
+            seen_at: now(),
+        };
+        pomodoro.start_cycle().await?;
+        pomodoro.save().await?;
+        pomodoro.run_hook(Event::WorkStart);
+        Ok(pomodoro)
     }
-}
 
-#[async_trait]
-impl State for Pomodoro {
-    async fn tick(&mut self) -> AppResult<()> {
-        self.current.update();
-        if self.current.is_finished() {
-            self.change_timers().await?
+    /// Rebuilds the pomo that was running when the app last exited.
+    pub async fn restore(active: db::ActiveTimer) -> AppResult<Self> {
+        let cycle_id = active.cycle_id;
+        let mut pomodoro = Self::load(active).await?;
+        // timers saved before every cycle was recorded won't have one yet
+        if cycle_id.is_none() {
+            pomodoro.start_cycle().await?;
         }
+        // keep it paused where it stopped, however long it's picked up for
+        pomodoro.save().await?;
+        Ok(pomodoro)
+    }
+
+    /// Like `restore`, but only reads the DB, e.g. to report on the pomo.
+    ///
+    /// The pomo didn't go on while the app was gone, so unless it was already
+    /// paused, or waiting on the user, it's paused from when it was last seen.
+    pub async fn load(active: db::ActiveTimer) -> AppResult<Self> {
+        let task = match active.task_id {
+            Some(id) => db::read_task(id).await?,
+            None => Task {
+                work_secs: active.work_secs as u64,
+                short_break_secs: active.short_break_secs as u64,
+                long_break_secs: active.long_break_secs as u64,
+                long_break_interval: active.long_break_interval as u32,
+                ..Task::default()
+            },
+        };
+        let mut current = Timer {
+            started_at: active.started_at,
+            dur: Duration::from_secs(active.dur_secs as u64),
+            elapsed: Duration::from_secs(0),
+            paused_for: Duration::from_secs(active.paused_secs as u64),
+            paused_at: match active.waiting_since {
+                Some(_) => active.paused_at,
+                None => active.paused_at.or(Some(active.seen_at)),
+            },
+        };
+        current.update();
+        Ok(Self {
+            id: None,
+            current,
+            task,
+            state: active.phase.parse()?,
+            session_id: active.session_id,
+            session_pomos: active.session_pomos as u32,
+            cycle_id: active.cycle_id.unwrap_or_default(),
+            previous: None,
+            waiting_since: active.waiting_since,
+            reminded_at: None,
+            interruptions: match active.cycle_id {
+                Some(id) => db::count_interruptions(id).await?,
+                None => Interruptions::default(),
+            },
+            seen_at: active.seen_at,
+        })
+    }
+
+    async fn save(&mut self) -> sqlx::Result<()> {
+        self.seen_at = now();
+        db::save_active_timer(&db::ActiveTimer {
+            task_id: self.task.id.map(|i| i as i64),
+            session_id: self.session_id,
+            phase: self.state.as_str().into(),
+            started_at: self.current.started_at,
+            dur_secs: self.current.dur.as_secs() as i64,
+            paused_secs: self.current.paused_for.as_secs() as i64,
+            paused_at: self.current.paused_at,
+            session_pomos: self.session_pomos as i64,
+            work_secs: self.task.work_secs as i64,
+            short_break_secs: self.task.short_break_secs as i64,
+            long_break_secs: self.task.long_break_secs as i64,
+            long_break_interval: self.task.long_break_interval as i64,
+            seen_at: self.seen_at,
+            cycle_id: Some(self.cycle_id),
+            waiting_since: self.waiting_since,
+        })
+        .await
+    }
+
+    async fn start_cycle(&mut self) -> sqlx::Result<()> {
+        self.cycle_id = db::start_cycle(
+            self.task.id.map(|i| i as i64),
+            self.session_id,
+            self.state.as_str(),
+            self.current.started_at,
+            self.current.dur.as_secs() as i64,
+        )
+        .await?;
         Ok(())
     }
 
-    fn should_finish(&self) -> bool {
-        self.should_finish
+    async fn finish_cycle(&self, outcome: Outcome) -> sqlx::Result<()> {
+        db::finish_cycle(
+            self.cycle_id,
+            outcome,
+            self.current.elapsed.min(self.current.dur).as_secs() as i64,
+            self.current.paused_for.as_secs() as i64,
+        )
+        .await
+    }
+
+    /// Closes the session; there's nothing left to resume afterwards.
+    async fn end(&self) -> sqlx::Result<()> {
+        db::end_session(self.session_id).await?;
+        db::clear_active_timer().await
     }
 
-    fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
-        if self.show_help {
-            let help_chunk = centered_rect(50, 7, frame.size());
-            let help_text = Paragraph::new(HELP_TEXT)
-                .block(
-                    Block::default()
-                        .title("Help")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                )
-                .style(Style::default().fg(Color::Yellow));
-            frame.render_widget(help_text, help_chunk);
-            return;
+    /// Ends the session mid-cycle, recording the time spent on the cycle so far.
+    pub async fn abandon(&self) -> sqlx::Result<()> {
+        // a cycle waiting on the user has already finished
+        if self.waiting_since.is_some() {
+            self.acknowledge().await?;
+            return self.end().await;
         }
-        let (height, width) = if let Some(desc) = &self.task.desc {
-            (
-                POMO_HEIGHT + 1,
-                max(
-                    (desc.width() + "Working on: ".width() + 2) as u16,
-                    POMO_WIDTH,
-                ),
-            )
-        } else {
-            (POMO_HEIGHT, POMO_WIDTH)
-        };
+        self.finish_cycle(Outcome::Abandoned).await?;
+        self.end().await?;
+        self.run_hook(Event::end_of(self.state, Outcome::Abandoned));
+        Ok(())
+    }
 
-        let pomo_chunk = centered_rect(width, height, frame.size());
+    /// Ends the session without recording the current cycle.
+    pub async fn discard(&self) -> sqlx::Result<()> {
+        self.end().await?;
+        if self.waiting_since.is_some() {
+            return Ok(());
+        }
+        db::delete_cycle(self.cycle_id).await
+    }
 
-        let pause_text = if self.current.paused {
-            " — paused"
+    /// Moves on to the next cycle once the current one is up, or waits for
+    /// the user to do so with `timer.manual_advance`.
+    pub async fn tick(&mut self) -> AppResult<()> {
+        if let Some(since) = self.waiting_since {
+            self.remind(since);
+            return Ok(());
+        }
+        // a paused timer never runs out, even one picked up already overdue
+        if self.current.is_paused() {
+            return Ok(());
+        }
+        self.current.update();
+        if !self.current.is_finished() {
+            if now() - self.seen_at >= chrono::Duration::seconds(HEARTBEAT_SECS) {
+                self.mark_seen().await?;
+            }
+            return Ok(());
+        }
+        if config::get().timer.manual_advance {
+            self.end_phase(Outcome::Completed).await?;
+            self.waiting_since = Some(now());
+            self.save().await?;
+            self.run_hook(Event::end_of(self.state, Outcome::Completed));
+            notify::phase_over(self);
         } else {
-            ""
-        };
-
-        frame.render_widget(
-            Block::default()
-                .title(format!("{}{}", self.state, pause_text))
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(self.style()),
-            pomo_chunk,
-        );
+            self.change_timers(Outcome::Completed).await?
+        }
+        Ok(())
+    }
 
-        // split into info and gauge
-        let pomo_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(if self.task.desc.is_some() { 2 } else { 1 }),
-                Constraint::Length(2),
-            ])
-            .margin(1)
-            .split(pomo_chunk);
-
-        let task_text = if let Some(desc) = &self.task.desc {
-            format!("Working on: {}\n", desc)
-        } else {
-            "".into()
-        };
+    /// Records that the pomo is still going, so that if the app dies, only
+    /// the time until now counts towards it.
+    pub async fn mark_seen(&mut self) -> sqlx::Result<()> {
+        self.seen_at = now();
+        db::touch_active_timer(self.seen_at).await
+    }
 
-        let pomo_text = format!("{}Finished: {}", task_text, self.task.pomos_finished);
+    /// Sends another notification if the next cycle has been waiting on the
+    /// user for `notifications.reminder_mins`.
+    fn remind(&mut self, since: NaiveDateTime) {
+        let mins = config::get().notifications.reminder_mins;
+        let last = self.reminded_at.unwrap_or(since);
+        if mins > 0 && now() - last >= chrono::Duration::minutes(mins as i64) {
+            self.reminded_at = Some(now());
+            notify::phase_over(self);
+        }
+    }
 
-        let pomo_par = Paragraph::new(pomo_text).alignment(Alignment::Left);
+    pub fn is_waiting(&self) -> bool {
+        self.waiting_since.is_some()
+    }
 
-        frame.render_widget(pomo_par, pomo_chunks[0]);
+    pub async fn set_paused(&mut self, paused: bool) -> sqlx::Result<()> {
+        // there's nothing to pause between cycles
+        if self.current.is_paused() != paused && !self.is_waiting() {
+            self.current.toggle_pause();
+            self.save().await?;
+            self.run_hook(if paused { Event::Pause } else { Event::Resume });
+        }
+        Ok(())
+    }
 
-        let gauge = Gauge::default()
-            .block(Block::default().title(format!("Remaining: {}", self.current)))
-            .gauge_style(self.style())
-            .ratio(self.current.elapsed.as_secs_f64() / self.current.dur.as_secs_f64())
-            .use_unicode(true);
+    /// Adds `secs` to the current phase, or takes time off if negative.
+    /// Adding time to a phase waiting on the user snoozes it.
+    pub async fn extend(&mut self, secs: i64) -> AppResult<()> {
+        if self.is_waiting() {
+            if secs <= 0 {
+                return Err("the cycle is already over".into());
+            }
+            return self.snooze(secs as u64).await;
+        }
+        self.lengthen(secs).await?;
+        Ok(())
+    }
 
-        frame.render_widget(gauge, pomo_chunks[1]);
+    async fn lengthen(&mut self, secs: i64) -> sqlx::Result<()> {
+        let moved = self.current.extend(secs);
+        db::extend_cycle(self.cycle_id, moved).await?;
+        self.save().await
     }
 
-    async fn handle_key_event(mut self: Box<Self>, event: KeyEvent) -> AppResult<Box<dyn State>> {
-        match event.code {
-            KeyCode::Char('p') => self.current.toggle_pause(),
-            KeyCode::Char('n') => self.change_timers().await?,
-            KeyCode::Char('q') => self.should_finish = true,
-            KeyCode::Enter => {
-                if let Some(id) = self.task.id {
-                    db::complete(id as i64).await?;
-                }
-                return Ok(Box::new(TasksState::new().await?));
+    /// Goes back to the phase which just ended, giving it `secs` more from now.
+    pub async fn snooze(&mut self, secs: u64) -> AppResult<()> {
+        let snoozed = match self.waiting_since.take() {
+            // the wait doesn't count towards the cycle
+            Some(since) => {
+                self.current.paused_for += (now() - since).to_std().unwrap_or_default();
+                None
             }
-            KeyCode::Esc => return Ok(Box::new(TasksState::new().await?)),
-            KeyCode::Char('?') => {
-                if self.show_help || !self.current.paused {
-                    self.current.toggle_pause()
-                }
-                self.show_help = !self.show_help
+            None => {
+                let previous = self
+                    .previous
+                    .take()
+                    .ok_or("there's no finished phase to snooze")?;
+                self.state = previous.state;
+                let dropped = mem::replace(&mut self.current, previous.timer);
+                // nor does the time spent in the phase being dropped
+                self.current.paused_for +=
+                    (now() - dropped.started_at).to_std().unwrap_or_default();
+                Some(mem::replace(&mut self.cycle_id, previous.cycle_id))
             }
-            _ => {}
+        };
+        db::reopen_cycle(self.cycle_id).await?;
+        self.interruptions = db::count_interruptions(self.cycle_id).await?;
+        if self.state == PomodoroState::Work {
+            self.task.pomos_finished -= 1;
+            self.session_pomos -= 1;
+            db::set_session_finished(self.session_id, self.session_pomos as i64).await?;
+            if let Some(id) = self.task.id {
+                db::set_finished(id as i64, self.task.pomos_finished as i64).await?;
+            }
+        }
+        self.current.update();
+        let until = self.current.elapsed() + Duration::from_secs(secs);
+        let by = until.as_secs() as i64 - self.current.duration().as_secs() as i64;
+        // saving points the active timer away from the cycle being dropped
+        self.lengthen(by).await?;
+        if let Some(id) = snoozed {
+            db::delete_cycle(id).await?;
         }
-        Ok(self)
+        self.run_hook(Event::start_of(self.state));
+        Ok(())
     }
-}
 
-impl Pomodoro {
-    pub fn assign(self, task: Task) -> Self {
-        let mut current = Timer::new(Duration::from_secs(task.work_secs));
-        current.update();
-        Self {
-            task,
-            current,
-            ..self
+    /// Logs an interruption against the current work cycle.
+    pub async fn interrupt(&mut self, kind: Interruption, reason: Option<&str>) -> AppResult<()> {
+        if self.state != PomodoroState::Work || self.is_waiting() {
+            return Err("interruptions can only be logged while working".into());
+        }
+        db::log_interruption(self.cycle_id, kind, reason).await?;
+        match kind {
+            Interruption::Internal => self.interruptions.internal += 1,
+            Interruption::External => self.interruptions.external += 1,
+        }
+        Ok(())
+    }
+
+    /// Cuts the current cycle short and moves on to the next one, or starts
+    /// the next one if the current one is waiting on the user.
+    pub async fn skip(&mut self) -> AppResult<()> {
+        if self.is_waiting() {
+            self.acknowledge().await?;
+            self.start_next(self.next_state()).await?;
+            self.run_hook(Event::start_of(self.state));
+            return Ok(());
         }
+        self.change_timers(Outcome::Skipped).await
     }
 
-    async fn change_timers(&mut self) -> AppResult<()> {
-        (self.state, self.current) = match self.state {
-            PomodoroState::Work => {
-                self.task.pomos_finished += 1;
-                db::complete_cycle(self.task.id.map(|i| i as i64)).await?;
-                if let Some(id) = self.task.id {
-                    db::set_finished(id as i64, self.task.pomos_finished as i64).await?;
-                }
-                if self.task.pomos_finished % 4 == 0 {
-                    (
-                        PomodoroState::LongBreak,
-                        Timer::new(Duration::from_secs(self.task.long_break_secs)),
-                    )
-                } else {
-                    (
-                        PomodoroState::ShortBreak,
-                        Timer::new(Duration::from_secs(self.task.short_break_secs)),
-                    )
-                }
-            }
-            PomodoroState::ShortBreak | PomodoroState::LongBreak => (
-                PomodoroState::Work,
-                Timer::new(Duration::from_secs(self.task.work_secs)),
-            ),
+    /// Records how long the finished cycle waited on the user.
+    async fn acknowledge(&self) -> sqlx::Result<()> {
+        let Some(since) = self.waiting_since else {
+            return Ok(());
         };
-        self.state.notify()?;
-        self.current.update();
+        db::acknowledge_cycle(self.cycle_id, (now() - since).num_seconds()).await
+    }
+
+    /// Ends the session and marks its task as completed.
+    pub async fn complete(&self) -> sqlx::Result<()> {
+        self.abandon().await?;
+        if let Some(id) = self.task.id {
+            db::complete(id as i64).await?;
+            self.run_hook(Event::TaskComplete);
+        }
         Ok(())
     }
 
-    pub fn style(&self) -> Style {
+    fn run_hook(&self, event: Event) {
+        hooks::run(event, &self.task, Some(self.state));
+    }
+
+    /// Position within the current set of pomos, which ends with a long break.
+    pub fn set_position(&self) -> u32 {
+        let interval = self.task.long_break_interval;
         match self.state {
-            PomodoroState::Work => Style::default().fg(Color::Red),
-            PomodoroState::ShortBreak => Style::default().fg(Color::Green),
-            PomodoroState::LongBreak => Style::default().fg(Color::Blue),
+            PomodoroState::Work if !self.is_waiting() => self.session_pomos % interval,
+            // the pomo just finished still belongs to the set during its break
+            _ => (self.session_pomos + interval - 1) % interval + 1,
+        }
+    }
+
+    /// The phase to follow the current one.
+    pub fn next_state(&self) -> PomodoroState {
+        let mut pomos = self.session_pomos;
+        if self.state == PomodoroState::Work {
+            // counted once the work phase ends
+            if !self.is_waiting() {
+                pomos += 1;
+            }
+            if pomos % self.task.long_break_interval == 0 {
+                PomodoroState::LongBreak
+            } else {
+                PomodoroState::ShortBreak
+            }
+        } else {
+            PomodoroState::Work
         }
     }
+
+    /// Rough time until the task's estimated pomos are all finished, assuming
+    /// short breaks in between. `None` without an estimate or once it's met.
+    pub fn eta_secs(&self) -> Option<u64> {
+        let left = self
+            .task
+            .estimated_pomos?
+            .checked_sub(self.task.total_pomos())
+            .filter(|&left| left > 0)? as u64;
+        let (work, short) = (self.task.work_secs, self.task.short_break_secs);
+        let remaining = if self.is_waiting() {
+            0
+        } else {
+            self.current
+                .duration()
+                .saturating_sub(self.current.elapsed())
+                .as_secs()
+        };
+        Some(match self.state {
+            PomodoroState::Work if !self.is_waiting() => remaining + (left - 1) * (short + work),
+            // a break comes first when the work phase is over but waiting
+            PomodoroState::Work => left * (short + work),
+            _ => remaining + left * work + (left - 1) * short,
+        })
+    }
+
+    async fn change_timers(&mut self, outcome: Outcome) -> AppResult<()> {
+        let ended = self.state;
+        let next = self.next_state();
+        self.end_phase(outcome).await?;
+        self.start_next(next).await?;
+        hooks::spawn(
+            [
+                hooks::prepare(Event::end_of(ended, outcome), &self.task, Some(ended)),
+                hooks::prepare(Event::start_of(self.state), &self.task, Some(self.state)),
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        notify::phase_started(self);
+        Ok(())
+    }
+
+    /// Records the current cycle as over, counting the pomo if it was work.
+    async fn end_phase(&mut self, outcome: Outcome) -> sqlx::Result<()> {
+        self.finish_cycle(outcome).await?;
+        if self.state == PomodoroState::Work {
+            self.task.pomos_finished += 1;
+            self.session_pomos += 1;
+            db::set_session_finished(self.session_id, self.session_pomos as i64).await?;
+            if let Some(id) = self.task.id {
+                db::set_finished(id as i64, self.task.pomos_finished as i64).await?;
+            }
+            // only the pomo which goes over is announced
+            if self.task.estimated_pomos == Some(self.task.total_pomos() - 1) {
+                notify::over_estimate(self);
+            }
+        }
+        Ok(())
+    }
+
+    async fn start_next(&mut self, state: PomodoroState) -> sqlx::Result<()> {
+        let secs = match state {
+            PomodoroState::Work => self.task.work_secs,
+            PomodoroState::ShortBreak => self.task.short_break_secs,
+            PomodoroState::LongBreak => self.task.long_break_secs,
+        };
+        self.previous = Some(Phase {
+            state: mem::replace(&mut self.state, state),
+            timer: mem::replace(&mut self.current, Timer::new(Duration::from_secs(secs))),
+            cycle_id: self.cycle_id,
+        });
+        self.waiting_since = None;
+        self.reminded_at = None;
+        self.interruptions = Interruptions::default();
+        self.current.update();
+        self.start_cycle().await?;
+        self.save().await
+    }
 }
 
 pub fn centered_rect(width: u16, height: u16, r: Rect) -> Rect {
@@ -0,0 +1,107 @@
+use crate::{
+    args::Command,
+    daemon::Request,
+    db,
+    pomodoro::{centered_rect, Pomodoro},
+    states::{parse_args, AppResult, State},
+    timer_view::TimerView,
+};
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::io;
+use tui::{
+    prelude::CrosstermBackend,
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders, Paragraph},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+const PROMPT_TEXT: &str = "[r] - resume it
+[l] - log the time spent so far as an abandoned cycle
+[d] - discard it
+[q] - quit and decide later";
+
+/// Shown on launch when a pomo was left running by a previous run of the app.
+pub struct ResumeState {
+    pomodoro: Pomodoro,
+    /// What the user asked to launch, opened once the old pomo is dealt with.
+    command: Option<Command>,
+    should_finish: bool,
+}
+
+#[async_trait]
+impl State for ResumeState {
+    async fn tick(&mut self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn should_finish(&self) -> bool {
+        self.should_finish
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
+        let elapsed = self.pomodoro.current.elapsed().as_secs();
+        let text = format!(
+            "{} on {} was interrupted.\n{}m{}s done, {} to go.\n\n{}",
+            self.pomodoro.state,
+            self.pomodoro
+                .task
+                .desc
+                .as_deref()
+                .unwrap_or("an untitled session"),
+            elapsed / 60,
+            elapsed % 60,
+            self.pomodoro.current,
+            PROMPT_TEXT,
+        );
+        let width = text.lines().map(|line| line.width()).max().unwrap_or(0) + 2;
+        let height = text.lines().count() + 2;
+        let chunk = centered_rect(width as u16, height as u16, frame.size());
+        let prompt = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title("Unfinished pomo")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(prompt, chunk);
+    }
+
+    async fn handle_key_event(mut self: Box<Self>, event: KeyEvent) -> AppResult<Box<dyn State>> {
+        match event.code {
+            KeyCode::Char('r') | KeyCode::Enter => {
+                // a newly started daemon picks the pomo up from the DB
+                return Ok(Box::new(TimerView::start(Request::Resume).await?));
+            }
+            KeyCode::Char('l') => {
+                self.pomodoro.abandon().await?;
+                return self.next().await;
+            }
+            KeyCode::Char('d') => {
+                self.pomodoro.discard().await?;
+                return self.next().await;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.should_finish = true,
+            _ => {}
+        }
+        Ok(self)
+    }
+}
+
+impl ResumeState {
+    pub async fn new(active: db::ActiveTimer, command: Option<Command>) -> AppResult<Self> {
+        Ok(Self {
+            pomodoro: Pomodoro::restore(active).await?,
+            command,
+            should_finish: false,
+        })
+    }
+
+    async fn next(self) -> AppResult<Box<dyn State>> {
+        Ok(parse_args(self.command)
+            .await?
+            .ok_or("command doesn't open the UI")?)
+    }
+}
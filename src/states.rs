@@ -1,12 +1,23 @@
 use crate::{
-    args::{Command, Complete, Start, WorkOn},
-    db,
-    pomodoro::Pomodoro,
+    args::{
+        Archive, Command, Complete, CycleAction, Extend, Interrupt, List, Reopen, Rm, Start, WorkOn,
+    },
+    config,
+    daemon::{self, Request},
+    db, export,
+    hooks::{self, Event},
+    import,
+    resume::ResumeState,
+    stats, status,
     tasks::{Task, TasksState},
+    timer_view::TimerView,
 };
 use async_trait::async_trait;
 use crossterm::event::KeyEvent;
-use std::{error, io};
+use std::{
+    error,
+    io::{self, IsTerminal, Write},
+};
 use tui::{prelude::CrosstermBackend, Frame};
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
@@ -22,31 +33,139 @@ pub trait State {
 }
 
 pub async fn parse_args(args: Option<Command>) -> AppResult<Option<Box<dyn State>>> {
+    let opens_ui = matches!(
+        args,
+        None | Some(
+            Command::Start(Start { detach: false, .. })
+                | Command::WorkOn(WorkOn { detach: false, .. })
+        )
+    );
+    if opens_ui {
+        if daemon::is_running().await {
+            // show the pomo that's already going rather than the tasks page
+            if args.is_none() {
+                if let Some(status) = daemon::send(&Request::Status).await? {
+                    return Ok(Some(Box::new(TimerView::attach(Some(status)))));
+                }
+            }
+        } else if let Some(active) = db::read_active_timer().await? {
+            // offer to pick up a pomo left running before opening anything new
+            return Ok(Some(Box::new(ResumeState::new(active, args).await?)));
+        }
+    }
     let state: Box<dyn State> = if let Some(command) = args {
         match command {
             Command::Start(Start {
                 work_mins,
                 short_break_mins,
                 long_break_mins,
-            }) => Box::new(Pomodoro::default().assign(Task {
-                work_secs: work_mins * 60,
-                short_break_secs: short_break_mins * 60,
-                long_break_secs: long_break_mins * 60,
-                ..Task::default()
-            })),
-            Command::List => {
-                db::print_tasks().await?;
+                long_break_interval,
+                detach,
+            }) => {
+                let defaults = &config::get().durations;
+                let request = Request::Start {
+                    work_secs: work_mins.unwrap_or(defaults.work_mins) * 60,
+                    short_break_secs: short_break_mins.unwrap_or(defaults.short_break_mins) * 60,
+                    long_break_secs: long_break_mins.unwrap_or(defaults.long_break_mins) * 60,
+                    long_break_interval: long_break_interval
+                        .unwrap_or(defaults.long_break_interval),
+                };
+                if detach {
+                    daemon::control(request).await?;
+                    return Ok(None);
+                }
+                Box::new(TimerView::start(request).await?)
+            }
+            Command::List(List { sort, filter }) => {
+                db::print_tasks(&filter, sort).await?;
                 return Ok(None);
             }
             Command::Add(task) => {
                 db::write_from_add(task).await?;
                 return Ok(None);
             }
-            Command::WorkOn(WorkOn { id }) => {
-                Box::new(Pomodoro::default().assign(db::read_task(id).await?))
+            Command::Edit(edit) => {
+                db::write_from_edit(edit).await?;
+                return Ok(None);
+            }
+            Command::WorkOn(WorkOn { id, detach }) => {
+                if detach {
+                    daemon::control(Request::WorkOn { id }).await?;
+                    return Ok(None);
+                }
+                Box::new(TimerView::start(Request::WorkOn { id }).await?)
+            }
+            Command::Daemon => {
+                daemon::run().await?;
+                return Ok(None);
+            }
+            Command::Status(args) => {
+                status::print(args).await?;
+                return Ok(None);
+            }
+            Command::Pause => {
+                daemon::control(Request::Pause).await?;
+                return Ok(None);
+            }
+            Command::Resume => {
+                daemon::control(Request::Resume).await?;
+                return Ok(None);
+            }
+            Command::Skip => {
+                daemon::control(Request::Skip).await?;
+                return Ok(None);
+            }
+            Command::Extend(Extend { mins }) => {
+                daemon::control(Request::Extend { secs: mins * 60 }).await?;
+                return Ok(None);
+            }
+            Command::Interrupt(Interrupt { kind, reason }) => {
+                daemon::control(Request::Interrupt { kind, reason }).await?;
+                return Ok(None);
+            }
+            Command::Stop => {
+                daemon::control(Request::Stop).await?;
+                return Ok(None);
             }
             Command::Complete(Complete { id }) => {
-                db::complete(id).await?;
+                let mut id = id;
+                loop {
+                    let task = db::read_task(id).await?;
+                    db::complete(id).await?;
+                    // the hook would be killed if the process exited under it
+                    if let Some(hook) = hooks::run(Event::TaskComplete, &task, None) {
+                        hook.await?;
+                    }
+                    // offer to complete the parent, and then its parent in turn
+                    match db::finished_parent(id).await? {
+                        Some(parent) if confirm_parent(&parent)? => id = parent.id.unwrap() as i64,
+                        _ => break,
+                    }
+                }
+                return Ok(None);
+            }
+            Command::Reopen(Reopen { id }) => {
+                db::reopen(id).await?;
+                return Ok(None);
+            }
+            Command::Rm(Rm { id, cycles }) => {
+                db::delete_task(id, matches!(cycles, CycleAction::Keep)).await?;
+                return Ok(None);
+            }
+            Command::Archive(Archive { id }) => {
+                db::archive(id).await?;
+                return Ok(None);
+            }
+            Command::Stats(args) => {
+                stats::print(args).await?;
+                return Ok(None);
+            }
+            Command::Export(args) => {
+                export::export(args).await?;
+                return Ok(None);
+            }
+            Command::Import(args) => {
+                import::import(args).await?;
                 return Ok(None);
             }
         }
@@ -55,3 +174,25 @@ pub async fn parse_args(args: Option<Command>) -> AppResult<Option<Box<dyn State
     };
     Ok(Some(state))
 }
+
+/// Asks on the terminal whether to complete `parent`, whose subtasks are all
+/// done. Outside of a terminal it's left open, with a hint of how to close it.
+fn confirm_parent(parent: &Task) -> io::Result<bool> {
+    let id = parent.id.unwrap();
+    let desc = parent.desc.as_deref().unwrap_or_default();
+    if !io::stdin().is_terminal() {
+        println!(
+            "All subtasks of {}: {} are done, complete it with `pogodoro complete {}`",
+            id, desc, id
+        );
+        return Ok(false);
+    }
+    print!(
+        "All subtasks of {}: {} are done. Complete it too? [y/N] ",
+        id, desc
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,314 @@
+use crate::{
+    args::{self, Filter},
+    db::{self, Estimates, PeriodTotals, ProjectTotals, TaskTotals, Totals},
+    states::AppResult,
+};
+use chrono::{Duration, Local, NaiveDate};
+use serde::Serialize;
+
+/// Aggregate report over finished work cycles, as printed by `pogodoro stats`.
+///
+/// A pomo is a work cycle which was completed or skipped; focus time also
+/// includes abandoned work cycles.
+#[derive(Serialize)]
+pub struct Report {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Only cycles on tasks in this project were counted.
+    pub project: Option<String>,
+    /// Only cycles on tasks with this tag were counted.
+    pub tag: Option<String>,
+    pub totals: Totals,
+    pub averages: Averages,
+    pub streaks: Streaks,
+    pub days: Vec<PeriodTotals>,
+    pub weeks: Vec<PeriodTotals>,
+    pub months: Vec<PeriodTotals>,
+    /// By the hour of day work cycles ended, e.g. `09`.
+    pub hours: Vec<PeriodTotals>,
+    pub projects: Vec<ProjectTotals>,
+    pub tasks: Vec<TaskTotals>,
+    /// Over tasks completed in the range, rather than cycles.
+    pub estimates: Estimates,
+}
+
+#[derive(Serialize)]
+pub struct Averages {
+    /// Per day with at least one pomo.
+    pub pomos_per_day: f64,
+    pub focus_mins_per_day: f64,
+    /// Time actually spent in each work cycle.
+    pub mins_per_cycle: f64,
+    /// Fraction of work cycles which ran to the end.
+    pub completion_rate: f64,
+    /// Interruptions per work cycle.
+    pub internal_interruptions: f64,
+    pub external_interruptions: f64,
+}
+
+/// Runs of consecutive days with at least one pomo.
+#[derive(Serialize)]
+pub struct Streaks {
+    pub current: u32,
+    pub longest: u32,
+}
+
+pub async fn print(args: args::Stats) -> AppResult<()> {
+    let report = Report::new(args.from, args.to, args.filter).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        report.print();
+    }
+    Ok(())
+}
+
+impl Report {
+    pub async fn new(
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        filter: Filter,
+    ) -> sqlx::Result<Self> {
+        // dates are compared as text in SQLite, so keep these to four-digit years
+        let start = from.unwrap_or(NaiveDate::from_ymd_opt(1, 1, 1).unwrap());
+        let end = to.unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap());
+        let totals = db::totals(start, end, &filter).await?;
+        let days = db::totals_by_period("%Y-%m-%d", start, end, &filter).await?;
+        let weeks = db::totals_by_period("%Y-W%W", start, end, &filter).await?;
+        let months = db::totals_by_period("%Y-%m", start, end, &filter).await?;
+        let hours = db::totals_by_period("%H", start, end, &filter).await?;
+        let projects = db::totals_by_project(start, end, &filter).await?;
+        let tasks = db::totals_by_task(start, end, &filter).await?;
+        let estimates = db::estimates(start, end, &filter).await?;
+
+        let active_days: Vec<NaiveDate> = days
+            .iter()
+            .filter(|day| day.totals.pomos > 0)
+            .filter_map(|day| day.period.parse().ok())
+            .collect();
+        let today = Local::now().date_naive();
+        let streaks = Streaks::new(&active_days, to.map_or(today, |to| to.min(today)));
+        let averages = Averages::new(&totals, active_days.len());
+
+        Ok(Self {
+            from,
+            to,
+            project: filter.project,
+            tag: filter.tag,
+            totals,
+            averages,
+            streaks,
+            days,
+            weeks,
+            months,
+            hours,
+            projects,
+            tasks,
+            estimates,
+        })
+    }
+
+    fn print(&self) {
+        let range = match (self.from, self.to) {
+            (None, None) => "all time".to_string(),
+            (from, to) => format!(
+                "{} to {}",
+                from.map_or("the start".into(), |d| d.to_string()),
+                to.map_or("today".into(), |d| d.to_string())
+            ),
+        };
+        let filter: String = self
+            .project
+            .iter()
+            .map(|project| format!(", project {}", project))
+            .chain(self.tag.iter().map(|tag| format!(", tag {}", tag)))
+            .collect();
+        println!("Totals ({}{})", range, filter);
+        println!("  Pomos:        {}", self.totals.pomos);
+        println!("  Focused:      {}", format_secs(self.totals.focus_secs));
+        println!("  Extended:     {}", format_secs(self.totals.extended_secs));
+        println!(
+            "  Completed:    {}/{} work cycles ({:.0}%)",
+            self.totals.completed,
+            self.totals.cycles,
+            self.averages.completion_rate * 100.0
+        );
+        println!("Averages (per day with a pomo)");
+        println!("  Pomos:        {:.1}", self.averages.pomos_per_day);
+        println!(
+            "  Focused:      {}",
+            format_secs((self.averages.focus_mins_per_day * 60.0) as i64)
+        );
+        println!(
+            "  Work cycle:   {}",
+            format_secs((self.averages.mins_per_cycle * 60.0) as i64)
+        );
+        println!("Interruptions (per work cycle)");
+        println!(
+            "  Internal:     {} ({:.1})",
+            self.totals.internal_interruptions, self.averages.internal_interruptions
+        );
+        println!(
+            "  External:     {} ({:.1})",
+            self.totals.external_interruptions, self.averages.external_interruptions
+        );
+        println!("Streaks");
+        println!("  Current:      {} days", self.streaks.current);
+        println!("  Longest:      {} days", self.streaks.longest);
+        println!("Estimates (completed tasks)");
+        println!(
+            "  Tasks:        {} ({} within estimate)",
+            self.estimates.tasks, self.estimates.within_estimate
+        );
+        println!(
+            "  Pomos:        {} finished, {} estimated",
+            self.estimates.actual_pomos, self.estimates.estimated_pomos
+        );
+        println!("  Off by:       {:.1} pomos", self.estimates.mean_error);
+
+        for (title, periods) in [
+            ("By month", &self.months),
+            ("By week", &self.weeks),
+            ("By day", &self.days),
+        ] {
+            println!("\n{}", title);
+            for period in periods {
+                println!(
+                    "  {:<12} {:>4} pomos  {:>8}",
+                    period.period,
+                    period.totals.pomos,
+                    format_secs(period.totals.focus_secs)
+                );
+            }
+        }
+
+        println!("\nBy hour (interruptions per work cycle)");
+        for hour in &self.hours {
+            println!(
+                "  {}:00  {:>4} cycles  {:>4.1}' {:>4.1}-",
+                hour.period,
+                hour.totals.cycles,
+                per_cycle(hour.totals.internal_interruptions, &hour.totals),
+                per_cycle(hour.totals.external_interruptions, &hour.totals)
+            );
+        }
+
+        println!("\nBy project");
+        for project in &self.projects {
+            println!(
+                "  {:>4} pomos  {:>8}  {}",
+                project.totals.pomos,
+                format_secs(project.totals.focus_secs),
+                project.project.as_deref().unwrap_or("(no project)")
+            );
+        }
+
+        println!("\nBy task (interruptions per work cycle)");
+        for task in &self.tasks {
+            println!(
+                "  {:>4} pomos  {:>8}  {:>4.1}' {:>4.1}-  {}",
+                task.totals.pomos,
+                format_secs(task.totals.focus_secs),
+                per_cycle(task.totals.internal_interruptions, &task.totals),
+                per_cycle(task.totals.external_interruptions, &task.totals),
+                task.desc.as_deref().unwrap_or("(no task)")
+            );
+        }
+    }
+}
+
+impl Averages {
+    fn new(totals: &Totals, active_days: usize) -> Self {
+        let ratio = |a: i64, b: usize| if b == 0 { 0.0 } else { a as f64 / b as f64 };
+        Self {
+            pomos_per_day: ratio(totals.pomos, active_days),
+            focus_mins_per_day: ratio(totals.focus_secs, active_days) / 60.0,
+            mins_per_cycle: ratio(totals.focus_secs, totals.cycles as usize) / 60.0,
+            completion_rate: ratio(totals.completed, totals.cycles as usize),
+            internal_interruptions: per_cycle(totals.internal_interruptions, totals),
+            external_interruptions: per_cycle(totals.external_interruptions, totals),
+        }
+    }
+}
+
+impl Streaks {
+    /// `days` must be sorted. The current streak is still alive if it ended
+    /// the day before `today`, since today's pomos may not have happened yet.
+    fn new(days: &[NaiveDate], today: NaiveDate) -> Self {
+        let mut longest = 0;
+        let mut run = 0;
+        let mut prev: Option<NaiveDate> = None;
+        for &day in days {
+            run = match prev {
+                Some(prev) if day - prev == Duration::days(1) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            prev = Some(day);
+        }
+        let current = match prev {
+            Some(last) if today - last <= Duration::days(1) => run,
+            _ => 0,
+        };
+        Self { current, longest }
+    }
+}
+
+fn per_cycle(count: i64, totals: &Totals) -> f64 {
+    if totals.cycles == 0 {
+        0.0
+    } else {
+        count as f64 / totals.cycles as f64
+    }
+}
+
+/// Formats a length of time to the minute, e.g. `2h05m`.
+pub fn format_secs(secs: i64) -> String {
+    let mins = secs / 60;
+    if mins >= 60 {
+        format!("{}h{:02}m", mins / 60, mins % 60)
+    } else {
+        format!("{}m", mins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 10, day).unwrap()
+    }
+
+    fn streaks(days: &[u32], today: u32) -> (u32, u32) {
+        let days: Vec<_> = days.iter().map(|&day| date(day)).collect();
+        let streaks = Streaks::new(&days, date(today));
+        (streaks.current, streaks.longest)
+    }
+
+    #[test]
+    fn no_days_means_no_streaks() {
+        assert_eq!(streaks(&[], 10), (0, 0));
+    }
+
+    #[test]
+    fn longest_streak_is_the_longest_run_of_consecutive_days() {
+        assert_eq!(streaks(&[1, 2, 3, 5, 6, 8], 20), (0, 3));
+    }
+
+    #[test]
+    fn current_streak_ends_today() {
+        assert_eq!(streaks(&[1, 3, 4, 5], 5), (3, 3));
+    }
+
+    #[test]
+    fn current_streak_survives_until_the_end_of_the_next_day() {
+        assert_eq!(streaks(&[3, 4], 5), (2, 2));
+        assert_eq!(streaks(&[3, 4], 6), (0, 2));
+    }
+
+    #[test]
+    fn current_streak_can_be_shorter_than_the_longest() {
+        assert_eq!(streaks(&[1, 2, 3, 4, 9, 10], 10), (2, 4));
+    }
+}
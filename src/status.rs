@@ -0,0 +1,148 @@
+use crate::{
+    args,
+    daemon::{self, Request, Status},
+    db,
+    pomodoro::{format_remaining, Pomodoro},
+    states::AppResult,
+};
+use serde::Serialize;
+
+const DEFAULT_FORMAT: &str = "{phase} {remaining}";
+const PLACEHOLDERS: [&str; 9] = [
+    "phase",
+    "remaining",
+    "clock",
+    "elapsed",
+    "task",
+    "set",
+    "pomos",
+    "percentage",
+    "paused",
+];
+
+/// Output of `pogodoro status --json`, in the shape waybar's custom modules read.
+#[derive(Serialize)]
+struct Waybar {
+    text: String,
+    tooltip: String,
+    /// The phase (`work`, `short_break` or `long_break`), `paused`, `waiting`,
+    /// `interrupted` or `idle`.
+    class: String,
+    percentage: u8,
+}
+
+pub async fn print(args: args::Status) -> AppResult<()> {
+    let format = args.format.as_deref().unwrap_or(DEFAULT_FORMAT);
+    // check the template even when there's nothing to fill it with
+    render(format, None)?;
+    let status = read().await?;
+    if args.json {
+        let waybar = match &status {
+            Some(status) => Waybar {
+                text: render(format, Some(status))?,
+                tooltip: status.summary(),
+                class: if status.interrupted {
+                    "interrupted".into()
+                } else if status.waiting {
+                    "waiting".into()
+                } else if status.paused {
+                    "paused".into()
+                } else {
+                    status.phase.as_str().into()
+                },
+                percentage: percentage(status),
+            },
+            None => Waybar {
+                text: String::new(),
+                tooltip: "No pomo is running".into(),
+                class: "idle".into(),
+                percentage: 0,
+            },
+        };
+        println!("{}", serde_json::to_string(&waybar)?);
+    } else if args.format.is_some() {
+        // an empty line lets status bars hide the module
+        println!("{}", render(format, status.as_ref())?);
+    } else {
+        match status {
+            Some(status) => println!("{}", status.summary()),
+            None => println!("No pomo is running"),
+        }
+    }
+    Ok(())
+}
+
+/// Asks the daemon if it's running, and otherwise reports the pomo it was
+/// running when it stopped, if any, as interrupted.
+async fn read() -> AppResult<Option<Status>> {
+    if daemon::is_running().await {
+        return daemon::send(&Request::Status).await;
+    }
+    Ok(match db::read_active_timer().await? {
+        Some(active) => Some(Status {
+            interrupted: true,
+            ..Status::from(&Pomodoro::load(active).await?)
+        }),
+        None => None,
+    })
+}
+
+/// Fills in the `{placeholder}`s in `format`, or checks they're all known if
+/// `status` is `None`.
+fn render(format: &str, status: Option<&Status>) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or("unclosed `{` in --format")? + start;
+        let name = &rest[start + 1..end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder `{{{}}}` in --format, expected one of: {}",
+                name,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        if let Some(status) = status {
+            out.push_str(&placeholder(name, status));
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    // e.g. `{task}` outside of a task leaves a dangling space
+    Ok(if status.is_some() {
+        out.trim().into()
+    } else {
+        String::new()
+    })
+}
+
+fn placeholder(name: &str, status: &Status) -> String {
+    match name {
+        "phase" => status.phase.to_string(),
+        "remaining" => format_remaining(status.remaining_secs),
+        "clock" => format!(
+            "{:02}:{:02}",
+            status.remaining_secs / 60,
+            status.remaining_secs % 60
+        ),
+        "elapsed" => format_remaining(status.elapsed_secs),
+        "task" => status.task.clone().unwrap_or_default(),
+        "set" => format!("{}/{}", status.set_position, status.long_break_interval),
+        "pomos" => status.pomos_finished.to_string(),
+        "percentage" => percentage(status).to_string(),
+        "paused" => if status.interrupted {
+            "interrupted"
+        } else if status.paused {
+            "paused"
+        } else {
+            ""
+        }
+        .into(),
+        _ => unreachable!(),
+    }
+}
+
+fn percentage(status: &Status) -> u8 {
+    (status.elapsed_secs * 100 / status.duration_secs.max(1)).min(100) as u8
+}
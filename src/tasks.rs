@@ -1,16 +1,27 @@
 use crate::{
+    args::Filter,
+    config,
+    cycle_log::CycleLog,
+    daemon::Request,
     db,
-    pomodoro::{centered_rect, Pomodoro},
+    history::HistoryState,
+    hooks::{self, Event},
+    pomodoro::centered_rect,
     states::{AppResult, State},
+    timer_view::TimerView,
 };
 
 use async_trait::async_trait;
-use chrono::{Duration, Local, NaiveDateTime};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use sqlx::{sqlite::SqliteRow, FromRow, Row};
 use std::{
-    io,
-    iter::repeat,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt, io,
+    iter::repeat_n,
+    mem,
     ops::{Deref, DerefMut},
 };
 use tui::{
@@ -18,7 +29,7 @@ use tui::{
     layout::{Constraint, Direction, Layout},
     prelude::{Alignment, CrosstermBackend, Rect},
     style::{Color, Modifier, Style},
-    text::Text,
+    text::{Line, Span, Text},
     widgets::{
         block::Title, BarChart, Block, BorderType, Borders, Cell, Clear, Paragraph,
         Row as TableRow, Table, TableState,
@@ -27,6 +38,61 @@ use tui::{
 };
 use unicode_width::UnicodeWidthStr;
 
+/// How urgent a task is, stored as 0 (none) to 3 (high).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn from_level(level: i64) -> Self {
+        match level {
+            3.. => Self::High,
+            2 => Self::Medium,
+            1 => Self::Low,
+            _ => Self::None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    pub fn level(&self) -> i64 {
+        *self as i64
+    }
+
+    /// The todo.txt priority it's shown as, e.g. `(A)` for high.
+    pub fn marker(&self) -> Option<&'static str> {
+        match self {
+            Self::High => Some("(A)"),
+            Self::Medium => Some("(B)"),
+            Self::Low => Some("(C)"),
+            Self::None => None,
+        }
+    }
+
+    /// Reads `high`, `h`, `(A)` and so on, or a blank for no priority.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "" | "none" => Some(Self::None),
+            "h" | "high" | "(a)" => Some(Self::High),
+            "m" | "medium" | "(b)" => Some(Self::Medium),
+            "l" | "low" | "(c)" => Some(Self::Low),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Task {
     pub id: Option<u32>,
@@ -34,35 +100,72 @@ pub struct Task {
     pub work_secs: u64,
     pub short_break_secs: u64,
     pub long_break_secs: u64,
+    /// Number of pomos to finish before each long break.
+    pub long_break_interval: u32,
     pub pomos_finished: u32,
     pub completed: Option<NaiveDateTime>,
+    pub project: Option<String>,
+    /// Sorted, without duplicates (ignoring case).
+    pub tags: Vec<String>,
+    pub priority: Priority,
+    pub due: Option<NaiveDate>,
+    /// Pomos the task is expected to take.
+    pub estimated_pomos: Option<u32>,
+    /// Task this is a subtask of, if any.
+    pub parent_id: Option<u32>,
+    /// Pomos finished on the task's subtasks, and on theirs in turn.
+    pub subtask_pomos: u32,
 }
 
-impl ToString for Task {
-    fn to_string(&self) -> String {
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // we only call this function when looking at tasks
         // in the DB, so unwrapping is ok
-        format!(
-            "{:>3}: {} || {}/{}/{}",
+        write!(
+            f,
+            "{:>3}: {}{}{}{} || {}/{}/{} x{}{}",
             self.id.unwrap(),
+            self.priority
+                .marker()
+                .map_or(String::new(), |marker| format!("{} ", marker)),
             self.desc.as_ref().unwrap(),
+            self.labels(),
+            self.due
+                .map_or(String::new(), |due| format!(" due:{}", due)),
             Self::format_time(self.work_secs),
             Self::format_time(self.short_break_secs),
             Self::format_time(self.long_break_secs),
+            self.long_break_interval,
+            match self.estimated_pomos {
+                Some(_) if self.is_over_estimate() => {
+                    format!(" || {} pomos, over estimate", self.progress())
+                }
+                Some(_) => format!(" || {} pomos", self.progress()),
+                None => String::new(),
+            }
         )
     }
 }
 
 impl Default for Task {
     fn default() -> Self {
+        let defaults = &config::get().durations;
         Self {
             id: None,
             desc: None,
-            work_secs: 25 * 60,
-            short_break_secs: 5 * 60,
-            long_break_secs: 15 * 60,
+            work_secs: defaults.work_mins * 60,
+            short_break_secs: defaults.short_break_mins * 60,
+            long_break_secs: defaults.long_break_mins * 60,
+            long_break_interval: defaults.long_break_interval,
             pomos_finished: 0,
             completed: None,
+            project: None,
+            tags: Vec::new(),
+            priority: Priority::None,
+            due: None,
+            estimated_pomos: None,
+            parent_id: None,
+            subtask_pomos: 0,
         }
     }
 }
@@ -84,17 +187,33 @@ impl FromRow<'_, SqliteRow> for Task {
                 .try_get::<i64, &str>("long_break_secs")?
                 .try_into()
                 .unwrap(),
+            long_break_interval: row
+                .try_get::<i64, &str>("long_break_interval")?
+                .try_into()
+                .unwrap(),
             pomos_finished: row
                 .try_get::<i64, &str>("pomos_finished")?
                 .try_into()
                 .unwrap(),
             completed: row.try_get("completed")?,
+            project: row.try_get("project")?,
+            tags: parse_tags(row.try_get::<Option<&str>, &str>("tags")?),
+            priority: Priority::from_level(row.try_get("priority")?),
+            due: row.try_get("due")?,
+            estimated_pomos: row
+                .try_get::<Option<i64>, &str>("estimated_pomos")?
+                .map(|pomos| pomos.try_into().unwrap()),
+            parent_id: row.try_get("parent_id")?,
+            subtask_pomos: row
+                .try_get::<i64, &str>("subtask_pomos")?
+                .try_into()
+                .unwrap(),
         })
     }
 }
 
 impl Task {
-    fn format_time(seconds: u64) -> String {
+    pub fn format_time(seconds: u64) -> String {
         let mins = seconds / 60;
         let secs = seconds % 60;
 
@@ -105,21 +224,202 @@ impl Task {
         }
     }
 
-    fn to_table_row(&self) -> TableRow {
+    /// The task's project and tags in todo.txt style, e.g. ` +acme @email`,
+    /// or an empty string if it has neither.
+    pub fn labels(&self) -> String {
+        let project = self.project.iter().map(|project| format!(" +{}", project));
+        let tags = self.tags.iter().map(|tag| format!(" @{}", tag));
+        project.chain(tags).collect()
+    }
+
+    /// Project and tag names are compared ignoring case, as in the DB.
+    pub fn matches(&self, filter: &Filter) -> bool {
+        let same = |a: &String, b: &String| a.to_lowercase() == b.to_lowercase();
+        filter
+            .project
+            .as_ref()
+            .is_none_or(|project| self.project.as_ref().is_some_and(|own| same(own, project)))
+            && filter
+                .tag
+                .as_ref()
+                .is_none_or(|tag| self.tags.iter().any(|own| same(own, tag)))
+    }
+
+    /// Pomos finished on the task and all of its subtasks.
+    pub fn total_pomos(&self) -> u32 {
+        self.pomos_finished + self.subtask_pomos
+    }
+
+    /// Pomos finished, out of the estimate if there is one, e.g. `3/5`.
+    pub fn progress(&self) -> String {
+        match self.estimated_pomos {
+            Some(estimate) => format!("{}/{}", self.total_pomos(), estimate),
+            None => self.total_pomos().to_string(),
+        }
+    }
+
+    pub fn is_over_estimate(&self) -> bool {
+        self.estimated_pomos
+            .is_some_and(|estimate| self.total_pomos() > estimate)
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        self.completed.is_none() && self.due.is_some_and(|due| due < Local::now().date_naive())
+    }
+
+    /// Most urgent first: by priority, then by due date.
+    pub fn cmp_priority(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| self.cmp_due_date(other))
+    }
+
+    /// Soonest due first, then by priority.
+    pub fn cmp_due(&self, other: &Self) -> Ordering {
+        self.cmp_due_date(other)
+            .then_with(|| other.priority.cmp(&self.priority))
+    }
+
+    /// Tasks without a due date go last.
+    fn cmp_due_date(&self, other: &Self) -> Ordering {
+        (self.due.is_none(), self.due).cmp(&(other.due.is_none(), other.due))
+    }
+
+    /// `depth` is how many of the task's parents are shown above it, and
+    /// `matched` the characters of its name to pick out as matching a search.
+    fn to_table_row(&self, depth: usize, matched: &[usize]) -> TableRow<'_> {
+        let indent = match depth {
+            0 => String::new(),
+            depth => format!("{}└ ", "  ".repeat(depth - 1)),
+        };
+        let marker = self
+            .priority
+            .marker()
+            .map_or(String::new(), |marker| format!("{} ", marker));
+        let mut name = vec![Span::raw(format!("{}{}", indent, marker))];
+        // TODO: come back and fix this unwrap when checking is done on task input
+        name.extend(highlight(self.desc.as_deref().unwrap(), matched));
+        name.push(Span::raw(self.labels()));
         let cells = [
-            // TODO: come back and fix this unwrap when checking is done on task input
-            Cell::from(self.desc.clone().unwrap()),
+            Cell::from(Line::from(name)),
             Cell::from(Self::format_time(self.work_secs)),
             Cell::from(Self::format_time(self.short_break_secs)),
             Cell::from(Self::format_time(self.long_break_secs)),
+            Cell::from(self.long_break_interval.to_string()),
+            Cell::from(self.progress()).style(if self.is_over_estimate() {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            }),
+            Cell::from(
+                self.due
+                    .map_or(String::new(), |due| due.format("%d/%m").to_string()),
+            ),
         ];
-        TableRow::new(cells)
+        let row = TableRow::new(cells);
+        if self.is_overdue() {
+            row.style(Style::default().fg(Color::LightMagenta))
+        } else {
+            row
+        }
     }
 }
 
+/// Splits tags given as e.g. `"email, admin"` or `["email", "admin"]` into
+/// single words, sorted and without duplicates (ignoring case).
+pub fn parse_tags<'a>(texts: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut tags: Vec<String> = texts
+        .into_iter()
+        .flat_map(|text| text.split(|c: char| c.is_whitespace() || c == ','))
+        .map(|tag| tag.trim_start_matches('@'))
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect();
+    tags.sort_by_key(|tag| tag.to_lowercase());
+    tags.dedup_by(|a, b| a.to_lowercase() == b.to_lowercase());
+    tags
+}
+
+/// Orders `tasks` so that each one's subtasks come straight after it, and
+/// otherwise keeps their order. Each task is paired with how many of its
+/// parents are also in `tasks`.
+pub fn nest(tasks: Vec<Task>) -> Vec<(usize, Task)> {
+    let ids: HashSet<u32> = tasks.iter().filter_map(|task| task.id).collect();
+    let (roots, mut rest): (Vec<_>, Vec<_>) = tasks
+        .into_iter()
+        .partition(|task| task.parent_id.is_none_or(|parent| !ids.contains(&parent)));
+    let mut nested = Vec::new();
+    for root in roots {
+        push_nested(root, 0, &mut rest, &mut nested);
+    }
+    nested
+}
+
+fn push_nested(task: Task, depth: usize, rest: &mut Vec<Task>, nested: &mut Vec<(usize, Task)>) {
+    let id = task.id;
+    nested.push((depth, task));
+    let (subtasks, others) = std::mem::take(rest)
+        .into_iter()
+        .partition(|other| id.is_some() && other.parent_id == id);
+    *rest = others;
+    for subtask in subtasks {
+        push_nested(subtask, depth + 1, rest, nested);
+    }
+}
+
+/// Splits `text` into spans, picking out the characters at `positions`
+/// (which are in order).
+fn highlight(text: &str, positions: &[usize]) -> Vec<Span<'static>> {
+    let style = |matched| {
+        if matched {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::UNDERLINED)
+        } else {
+            Style::default()
+        }
+    };
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&i).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            spans.push(Span::styled(mem::take(&mut run), style(run_matched)));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, style(run_matched)));
+    }
+    spans
+}
+
+/// Trims a project name, treating a blank one as no project.
+pub fn parse_project(text: &str) -> Option<String> {
+    let project = text.trim().trim_start_matches('+').trim();
+    (!project.is_empty()).then(|| project.to_string())
+}
+
 pub struct TasksState {
     task_tables: TaskTableGroup,
     input: TaskInput,
+    /// Only tasks matching this are shown.
+    filter: Filter,
+    filter_input: InputGroup,
+    /// Narrows the tables down further as it's typed, see [`Search`].
+    search: String,
+    /// Task being changed by the form, if it isn't creating a new one.
+    editing: Option<Task>,
+    /// Task the form is creating a subtask of.
+    parent: Option<Task>,
+    /// Parent whose last open subtask was just completed, offered to be
+    /// completed too.
+    finished_parent: Option<Task>,
+    /// Why the selected task couldn't be deleted.
+    delete_error: Option<String>,
     cycles: Vec<(String, usize)>,
     input_state: InputState,
     should_finish: bool,
@@ -129,6 +429,14 @@ pub enum InputState {
     Insert,
     Normal,
     Help,
+    /// Asking what to do with the selected task's cycles before deleting it.
+    Delete,
+    /// Typing a project or tag to narrow the tables down to.
+    Filter,
+    /// Asking whether to complete `finished_parent`.
+    CompleteParent,
+    /// Typing a search, which filters the tables as it goes.
+    Search,
 }
 
 const HELP_TEXT: &str = "This screen has two modes: insert, and normal.
@@ -140,6 +448,20 @@ The app begins in normal mode.
 Use [tab] or [i] to enter insert mode,
 [tab] to switch between fields, and [enter] to submit the task.
 Use [esc] to exit insert mode into normal mode.
+Use [e] in normal mode to edit the selected task in the same form,
+[d] to delete it, and [a] to archive it (hiding it but keeping its stats).
+Use [r] on a completed task to reopen it.
+Use [s] to add a subtask to the selected task; subtasks are shown
+under their parent, whose pomos include theirs.
+Tasks are sorted by priority, then due date; overdue ones are highlighted.
+Use [f] to only show tasks in a project or with a tag,
+e.g. \"+acme\" or \"@email\", or clear the filter to show them all again.
+Use [L] to see the latest work cycles and write notes on them.
+Use [H] to page through every completed task, by the day it was done.
+Use [/] to search the tables as you type: words fuzzy-match task names,
+and \"+acme\" or \"@em\" keep tasks whose project or a tag starts so.
+[enter] keeps the search, [n] / [N] then jump between the matches,
+and [esc] clears it.
 
 While in normal mode, use [j], [k], [up], and [down]
 to navigate task entries in the main box.
@@ -148,6 +470,13 @@ You can also exit the program from normal mode with [q] or [esc].
 
 Use [?] to quit this help message into normal mode.";
 
+const DELETE_TEXT: &str = "[k] - keep its cycles in stats, without a task
+[d] - delete its cycles too
+[esc] - cancel";
+
+const COMPLETE_PARENT_TEXT: &str = "[y] - complete it too
+[n] - leave it open";
+
 #[async_trait]
 impl State for TasksState {
     async fn tick(&mut self) -> AppResult<()> {
@@ -159,18 +488,37 @@ impl State for TasksState {
     }
 
     fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
+        let chart_height = if config::get().ui.show_barchart {
+            30
+        } else {
+            0
+        };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Percentage(30)])
+            .constraints([Constraint::Min(0), Constraint::Percentage(chart_height)])
             .margin(1)
             .split(frame.size());
 
-        self.task_tables.render_on(frame, chunks[0]);
-        self.render_barchart(frame, chunks[1]);
+        if self.search.is_empty() && !matches!(self.input_state, InputState::Search) {
+            self.task_tables.render_on(frame, chunks[0]);
+        } else {
+            let table_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(3)])
+                .split(chunks[0]);
+            self.task_tables.render_on(frame, table_chunks[0]);
+            self.render_search(frame, table_chunks[1]);
+        }
+        if chart_height > 0 {
+            self.render_barchart(frame, chunks[1]);
+        }
 
         match self.input_state {
             InputState::Insert => self.input.render_on(frame),
             InputState::Help => self.render_help(frame),
+            InputState::Delete => self.render_delete(frame),
+            InputState::Filter => self.filter_input.render_on(frame),
+            InputState::CompleteParent => self.render_complete_parent(frame),
             _ => {}
         }
     }
@@ -185,11 +533,66 @@ impl State for TasksState {
                     self.input_state = InputState::Insert;
                     self.input.next()
                 }
+                KeyCode::Char('e') => {
+                    if let Some(task) = self.task_tables.selected() {
+                        let task = task.clone();
+                        self.input.fill(&task);
+                        self.editing = Some(task);
+                        self.task_tables.focused = None;
+                        self.input_state = InputState::Insert;
+                        self.input.next()
+                    }
+                }
+                KeyCode::Char('s') => {
+                    if let Some(task) = self.task_tables.selected() {
+                        let task = task.clone();
+                        self.input.title =
+                            format!("Create subtask of {}", task.desc.as_deref().unwrap());
+                        self.parent = Some(task);
+                        self.task_tables.focused = None;
+                        self.input_state = InputState::Insert;
+                        self.input.next()
+                    }
+                }
+                KeyCode::Char('L') => return Ok(Box::new(CycleLog::new().await?)),
+                KeyCode::Char('H') => return Ok(Box::new(HistoryState::new().await?)),
+                KeyCode::Char('/') => self.input_state = InputState::Search,
+                KeyCode::Char('n') => self.task_tables.next_match(),
+                KeyCode::Char('N') => self.task_tables.prev_match(),
+                KeyCode::Esc if !self.search.is_empty() => {
+                    self.search.clear();
+                    self.apply_search()
+                }
+                KeyCode::Char('f') => {
+                    self.filter_input.inputs[0].text = filter_text(&self.filter);
+                    self.filter_input.focused = Some(0);
+                    self.input_state = InputState::Filter
+                }
+                KeyCode::Char('d') if self.task_tables.selected().is_some() => {
+                    self.input_state = InputState::Delete
+                }
+                KeyCode::Char('a') => {
+                    if let Some(task) = self.task_tables.selected() {
+                        db::archive(task.id.unwrap() as i64).await?;
+                        return Ok(Box::new(self.reload().await?));
+                    }
+                }
+                // undo a completion made by mistake
+                KeyCode::Char('r') => {
+                    if let Some(task) = self.task_tables.selected() {
+                        if task.completed.is_some() {
+                            db::reopen(task.id.unwrap() as i64).await?;
+                            return Ok(Box::new(self.reload().await?));
+                        }
+                    }
+                }
                 // allow user to complete task
                 KeyCode::Char('c') => {
                     if let Some(task) = self.task_tables.selected() {
-                        db::complete(task.id.unwrap() as i64).await?;
-                        return Ok(Box::new(Self::new().await?));
+                        let id = task.id.unwrap() as i64;
+                        db::complete(id).await?;
+                        hooks::run(Event::TaskComplete, task, None);
+                        return Ok(Box::new(self.reload().await?.offer_parent(id).await?));
                     }
                 }
                 KeyCode::Down | KeyCode::Char('j') => self.task_tables.next_task(),
@@ -198,7 +601,8 @@ impl State for TasksState {
                 KeyCode::BackTab | KeyCode::Char('h') => self.task_tables.previous(),
                 KeyCode::Enter => {
                     if let Some(task) = self.task_tables.selected() {
-                        return Ok(Box::new(Pomodoro::default().assign(task.clone())));
+                        let id = task.id.unwrap() as i64;
+                        return Ok(Box::new(TimerView::start(Request::WorkOn { id }).await?));
                     }
                 }
                 _ => {}
@@ -208,16 +612,44 @@ impl State for TasksState {
                     KeyCode::Char(c) => self.input.push(c),
                     KeyCode::Esc => {
                         self.input_state = InputState::Normal;
-                        self.input.0.focused = None
+                        self.input.0.focused = None;
+                        // don't leave an abandoned edit in the form for the next new task
+                        if self.editing.take().is_some() || self.parent.take().is_some() {
+                            self.input = TaskInput::default();
+                        }
                     }
                     KeyCode::Tab => self.input.next(),
                     KeyCode::BackTab => self.input.previous(),
+                    KeyCode::Enter if self.editing.is_some() => {
+                        let task = self.editing.take().unwrap();
+                        db::update_task(&Task {
+                            id: task.id,
+                            pomos_finished: task.pomos_finished,
+                            completed: task.completed,
+                            parent_id: task.parent_id,
+                            ..self.input.get_task()
+                        })
+                        .await?;
+                        return Ok(Box::new(self.reload().await?));
+                    }
+                    KeyCode::Enter if self.parent.is_some() => {
+                        let parent = self.parent.take().unwrap();
+                        db::write_task(&Task {
+                            parent_id: parent.id,
+                            ..self.input.get_task()
+                        })
+                        .await?;
+                        return Ok(Box::new(self.reload().await?));
+                    }
                     KeyCode::Enter => {
-                        let (desc, work_secs, sb_secs, lb_secs) = self.input.get_task();
-                        let new_task = db::write_and_return_task(desc, work_secs, sb_secs, lb_secs)
+                        let new_task = db::write_and_return_task(&self.input.get_task())
                             .await
                             .unwrap();
-                        self.task_tables.add_task(new_task)
+                        // the task is still added, just not shown under a filter it's outside of
+                        if new_task.matches(&self.filter) {
+                            self.task_tables
+                                .add_task(new_task, &Search::parse(&self.search))
+                        }
                     }
                     KeyCode::Backspace => {
                         self.input.pop();
@@ -233,6 +665,71 @@ impl State for TasksState {
                     self.input_state = InputState::Normal
                 }
             }
+            InputState::Filter => match event.code {
+                KeyCode::Char(c) => self.filter_input.push(c),
+                KeyCode::Backspace => {
+                    self.filter_input.pop();
+                }
+                KeyCode::Esc => self.input_state = InputState::Normal,
+                KeyCode::Enter => {
+                    self.filter = parse_filter(&self.filter_input.inputs[0].text);
+                    return Ok(Box::new(self.reload().await?));
+                }
+                _ => {}
+            },
+            InputState::Search => match event.code {
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.apply_search()
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.apply_search()
+                }
+                KeyCode::Down => self.task_tables.next_match(),
+                KeyCode::Up => self.task_tables.prev_match(),
+                KeyCode::Enter => self.input_state = InputState::Normal,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.apply_search();
+                    self.input_state = InputState::Normal
+                }
+                _ => {}
+            },
+            InputState::Delete => match event.code {
+                KeyCode::Char(c @ ('k' | 'd')) if self.delete_error.is_none() => {
+                    let id = self.task_tables.selected().unwrap().id.unwrap();
+                    // the error isn't `Send`, so can't be held onto across the reload
+                    let deleted = db::delete_task(id as i64, c == 'k')
+                        .await
+                        .map_err(|err| err.to_string());
+                    match deleted {
+                        Ok(()) => return Ok(Box::new(self.reload().await?)),
+                        // e.g. it's the task the daemon is running
+                        Err(err) => self.delete_error = Some(err),
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.delete_error = None;
+                    self.input_state = InputState::Normal
+                }
+                _ => {}
+            },
+            InputState::CompleteParent => match event.code {
+                KeyCode::Char('y') => {
+                    let parent = self.finished_parent.take().unwrap();
+                    let id = parent.id.unwrap() as i64;
+                    db::complete(id).await?;
+                    hooks::run(Event::TaskComplete, &parent, None);
+                    // which may have been the last open subtask of its own parent
+                    return Ok(Box::new(self.reload().await?.offer_parent(id).await?));
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    self.finished_parent = None;
+                    self.input_state = InputState::Normal
+                }
+                _ => {}
+            },
         };
         Ok(self)
     }
@@ -240,11 +737,42 @@ impl State for TasksState {
 
 impl TasksState {
     pub async fn new() -> Result<Self, sqlx::Error> {
-        let tasks = crate::db::read_tasks().await?;
+        Self::with_filter(Filter::default()).await
+    }
+
+    /// Reads the tasks afresh, keeping the current filter and search.
+    async fn reload(&self) -> Result<Self, sqlx::Error> {
+        let mut state = Self::with_filter(self.filter.clone()).await?;
+        if !self.search.is_empty() {
+            state.search = self.search.clone();
+            state.apply_search();
+        }
+        Ok(state)
+    }
+
+    /// Shows only the tasks matching the search, selecting the first of them.
+    fn apply_search(&mut self) {
+        self.task_tables.search(&Search::parse(&self.search))
+    }
+
+    /// Asks whether to complete the parent of the task with ID `id`, which was
+    /// just completed, if that leaves the parent with no open subtasks.
+    pub async fn offer_parent(mut self, id: i64) -> Result<Self, sqlx::Error> {
+        if let Some(parent) = db::finished_parent(id).await? {
+            self.finished_parent = Some(parent);
+            self.input_state = InputState::CompleteParent;
+        }
+        Ok(self)
+    }
+
+    async fn with_filter(filter: Filter) -> Result<Self, sqlx::Error> {
+        let mut tasks = crate::db::read_tasks().await?;
+        tasks.retain(|task| task.matches(&filter));
+        tasks.sort_by(Task::cmp_priority);
         let (incomplete, complete): (Vec<_>, Vec<_>) =
             tasks.into_iter().partition(|t| t.completed.is_none());
         let (new, in_progress): (Vec<_>, Vec<_>) =
-            incomplete.into_iter().partition(|t| t.pomos_finished == 0);
+            incomplete.into_iter().partition(|t| t.total_pomos() == 0);
         let last_day_complete: Vec<_> = complete
             .into_iter()
             .filter(|t| {
@@ -254,13 +782,21 @@ impl TasksState {
                     <= Duration::hours(24)
             })
             .collect();
+        let suffix = if filter.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", filter_text(&filter))
+        };
         let task_tables = TaskTableGroup::new(vec![
-            (new, "New".into()),
-            (in_progress, "In Progress".into()),
-            (last_day_complete, "Completed in the last day".into()),
+            (new, format!("New{}", suffix)),
+            (in_progress, format!("In Progress{}", suffix)),
+            (
+                last_day_complete,
+                format!("Completed in the last day{}", suffix),
+            ),
         ]);
 
-        let cycles: Vec<_> = crate::db::last_n_day_cycles(30)
+        let cycles: Vec<_> = crate::db::last_n_day_cycles(config::get().ui.barchart_days)
             .await?
             .iter()
             .map(|(date, i)| (date.format("%d/%m").to_string(), *i))
@@ -269,6 +805,17 @@ impl TasksState {
         Ok(Self {
             task_tables,
             input: TaskInput::default(),
+            filter,
+            filter_input: InputGroup {
+                title: "Filter tasks".into(),
+                inputs: vec![UserInput::new("+project and/or @tag".into())],
+                focused: None,
+            },
+            search: String::new(),
+            editing: None,
+            parent: None,
+            finished_parent: None,
+            delete_error: None,
             input_state: InputState::Normal,
             cycles,
             should_finish: false,
@@ -300,9 +847,34 @@ impl TasksState {
         frame.render_widget(barchart, chunk);
     }
 
+    fn render_search<B: Backend>(&mut self, frame: &mut Frame<'_, B>, chunk: Rect) {
+        let typing = matches!(self.input_state, InputState::Search);
+        let title = if typing {
+            "Search (words, +project, @tag) [enter] keep, [esc] clear"
+        } else {
+            "Search [n] / [N] next / previous match, [/] change, [esc] clear"
+        };
+        let search = Paragraph::new(self.search.as_str())
+            .style(if typing {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            })
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            );
+        frame.render_widget(search, chunk);
+        if typing {
+            frame.set_cursor(chunk.x + self.search.width() as u16 + 1, chunk.y + 1);
+        }
+    }
+
     fn render_help<B: Backend>(&mut self, frame: &mut Frame<'_, B>) {
         // hard coded vals for text width and height
-        let help_chunk = centered_rect(70, 18, frame.size());
+        let help_chunk = centered_rect(78, 32, frame.size());
 
         let help_text = Paragraph::new(HELP_TEXT)
             .block(
@@ -315,6 +887,133 @@ impl TasksState {
         frame.render_widget(Clear, help_chunk);
         frame.render_widget(help_text, help_chunk);
     }
+
+    fn render_delete<B: Backend>(&mut self, frame: &mut Frame<'_, B>) {
+        let desc = self
+            .task_tables
+            .selected()
+            .and_then(|task| task.desc.as_deref())
+            .unwrap_or_default();
+        let text = match &self.delete_error {
+            Some(err) => format!("Can't delete \"{}\": {}\n\n[esc] - cancel", desc, err),
+            None => format!("Delete \"{}\"?\n\n{}", desc, DELETE_TEXT),
+        };
+        let width = text.lines().map(|line| line.width()).max().unwrap_or(0) + 2;
+        let chunk = centered_rect(width as u16, text.lines().count() as u16 + 2, frame.size());
+        let prompt = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title("Delete task")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(Clear, chunk);
+        frame.render_widget(prompt, chunk);
+    }
+
+    fn render_complete_parent<B: Backend>(&mut self, frame: &mut Frame<'_, B>) {
+        let desc = self
+            .finished_parent
+            .as_ref()
+            .and_then(|task| task.desc.as_deref())
+            .unwrap_or_default();
+        let text = format!(
+            "All subtasks of \"{}\" are done.\n\n{}",
+            desc, COMPLETE_PARENT_TEXT
+        );
+        let width = text.lines().map(|line| line.width()).max().unwrap_or(0) + 2;
+        let chunk = centered_rect(width as u16, text.lines().count() as u16 + 2, frame.size());
+        let prompt = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .title("Complete parent")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(Clear, chunk);
+        frame.render_widget(prompt, chunk);
+    }
+}
+
+/// Writes `filter` the way it's typed into the filter box, e.g. `+acme @email`.
+fn filter_text(filter: &Filter) -> String {
+    let project = filter.project.iter().map(|project| format!("+{}", project));
+    let tag = filter.tag.iter().map(|tag| format!("@{}", tag));
+    project.chain(tag).collect::<Vec<_>>().join(" ")
+}
+
+/// A search typed into the tasks page. `+project` and `@tag` words keep the
+/// tasks whose project or one of whose tags starts with them, and the other
+/// words are fuzzy-matched against the tasks' names.
+#[derive(Default)]
+struct Search {
+    projects: Vec<String>,
+    tags: Vec<String>,
+    pattern: Vec<char>,
+}
+
+impl Search {
+    fn parse(text: &str) -> Self {
+        let mut search = Self::default();
+        for word in text.split_whitespace() {
+            if let Some(project) = word.strip_prefix('+') {
+                search.projects.push(project.to_lowercase());
+            } else if let Some(tag) = word.strip_prefix('@') {
+                search.tags.push(tag.to_lowercase());
+            } else {
+                search.pattern.extend(word.chars());
+            }
+        }
+        search
+    }
+
+    /// The positions of the characters in `task`'s name matching the search,
+    /// or `None` if it doesn't match.
+    fn matches(&self, task: &Task) -> Option<Vec<usize>> {
+        let starts =
+            |own: &String, prefix: &String| own.to_lowercase().starts_with(prefix.as_str());
+        let labelled = self.projects.iter().all(|project| {
+            task.project
+                .as_ref()
+                .is_some_and(|own| starts(own, project))
+        }) && self
+            .tags
+            .iter()
+            .all(|tag| task.tags.iter().any(|own| starts(own, tag)));
+        if !labelled {
+            return None;
+        }
+        fuzzy_match(task.desc.as_deref().unwrap_or_default(), &self.pattern)
+    }
+}
+
+/// Finds `pattern`'s characters in `text` in the same order, though not
+/// necessarily next to each other, ignoring case. Returns where each was found.
+fn fuzzy_match(text: &str, pattern: &[char]) -> Option<Vec<usize>> {
+    let mut chars = text.chars().enumerate();
+    pattern
+        .iter()
+        .map(|p| {
+            chars
+                .find(|(_, c)| c.to_lowercase().eq(p.to_lowercase()))
+                .map(|(i, _)| i)
+        })
+        .collect()
+}
+
+/// Reads a filter typed as `+project` and/or `@tag`; other words are ignored.
+fn parse_filter(text: &str) -> Filter {
+    let mut filter = Filter::default();
+    for word in text.split_whitespace() {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            filter.project = Some(project.into());
+        } else if let Some(tag) = word.strip_prefix('@').filter(|t| !t.is_empty()) {
+            filter.tag = Some(tag.into());
+        }
+    }
+    filter
 }
 
 struct UserInput {
@@ -330,7 +1029,7 @@ impl UserInput {
         }
     }
 
-    fn to_widget(&self, focused: Option<bool>) -> Paragraph {
+    fn to_widget(&self, focused: Option<bool>) -> Paragraph<'_> {
         Paragraph::new(self.text.clone())
             .style(if let Some(true) = focused {
                 Style::default().fg(Color::Yellow)
@@ -360,6 +1059,7 @@ impl UserInput {
 
 #[derive(Default)]
 pub struct InputGroup {
+    title: String,
     inputs: Vec<UserInput>,
     focused: Option<usize>,
 }
@@ -388,7 +1088,9 @@ impl InputGroup {
         let height = self.inputs.len() * 3 + 2;
         let width = std::cmp::max(50, frame.size().width / 3);
         let outer_rect = centered_rect(width, height as u16, frame.size());
-        let outer_block = Block::default().title("Create task").borders(Borders::ALL);
+        let outer_block = Block::default()
+            .title(self.title.as_str())
+            .borders(Borders::ALL);
         let rect = outer_block.inner(outer_rect);
         frame.render_widget(Clear, outer_rect);
         frame.render_widget(outer_block, outer_rect);
@@ -396,9 +1098,7 @@ impl InputGroup {
         let sub_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
-                repeat(Constraint::Length(3))
-                    .take(self.inputs.len())
-                    .collect::<Vec<Constraint>>(),
+                repeat_n(Constraint::Length(3), self.inputs.len()).collect::<Vec<Constraint>>(),
             )
             .split(rect);
 
@@ -420,8 +1120,8 @@ impl InputGroup {
 
     // add a char to focused input
     fn push(&mut self, c: char) {
-        if self.focused.is_some() {
-            self.inputs[self.focused.unwrap()].push(c)
+        if let Some(idx) = self.focused {
+            self.inputs[idx].push(c)
         }
     }
 
@@ -432,8 +1132,8 @@ impl InputGroup {
     }
 
     fn clear(&mut self) {
-        if self.focused.is_some() {
-            self.inputs[self.focused.unwrap()].text = String::new()
+        if let Some(idx) = self.focused {
+            self.inputs[idx].text = String::new()
         }
     }
 }
@@ -443,11 +1143,18 @@ struct TaskInput(InputGroup);
 impl Default for TaskInput {
     fn default() -> Self {
         Self(InputGroup {
+            title: "Create task".into(),
             inputs: vec![
                 UserInput::new("Task name".into()),
                 UserInput::new("Work duration (m)".into()),
                 UserInput::new("Short break duration (m)".into()),
                 UserInput::new("Long break duration (m)".into()),
+                UserInput::new("Pomos before long break".into()),
+                UserInput::new("Project".into()),
+                UserInput::new("Tags (separated by spaces)".into()),
+                UserInput::new("Priority (high, medium or low)".into()),
+                UserInput::new("Due date (YYYY-MM-DD)".into()),
+                UserInput::new("Estimated pomos".into()),
             ],
             focused: None,
         })
@@ -511,27 +1218,81 @@ impl DerefMut for TaskInput {
 }
 
 impl TaskInput {
-    const DEFAULT_SECS: (u64, u64, u64) = (25 * 60, 5 * 60, 15 * 60);
-    fn parse_secs(&mut self, i: usize, default: u64) -> i64 {
+    fn parse_secs(&mut self, i: usize, default_mins: u64) -> u64 {
         let text: &mut String = &mut self.0.inputs[i].text;
-        (text
-            .drain(..)
-            .collect::<String>()
+        (std::mem::take(text)
             .parse::<f64>()
-            .unwrap_or((default / 60) as f64)
-            * 60.0) as i64
+            .unwrap_or(default_mins as f64)
+            * 60.0) as u64
     }
 
-    fn get_task(&mut self) -> (String, i64, i64, i64) {
-        let work_secs = self.parse_secs(1, Self::DEFAULT_SECS.0);
-        let short_break_secs = self.parse_secs(2, Self::DEFAULT_SECS.1);
-        let long_break_secs = self.parse_secs(3, Self::DEFAULT_SECS.2);
-        (
-            self.0.inputs[0].text.drain(..).collect(),
+    fn parse_interval(&mut self, i: usize, default: u32) -> u32 {
+        std::mem::take(&mut self.0.inputs[i].text)
+            .parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .unwrap_or(default)
+    }
+
+    /// Pre-fills the form with `task`, to be edited in place.
+    fn fill(&mut self, task: &Task) {
+        let mins = |secs: u64| (secs as f64 / 60.0).to_string();
+        self.0.title = "Edit task".into();
+        let texts = [
+            task.desc.clone().unwrap_or_default(),
+            mins(task.work_secs),
+            mins(task.short_break_secs),
+            mins(task.long_break_secs),
+            task.long_break_interval.to_string(),
+            task.project.clone().unwrap_or_default(),
+            task.tags.join(" "),
+            match task.priority {
+                Priority::None => String::new(),
+                priority => priority.as_str().into(),
+            },
+            task.due.map_or(String::new(), |due| due.to_string()),
+            task.estimated_pomos
+                .map_or(String::new(), |pomos| pomos.to_string()),
+        ];
+        for (input, text) in self.0.inputs.iter_mut().zip(texts) {
+            input.text = text;
+        }
+    }
+
+    fn get_task(&mut self) -> Task {
+        let defaults = &config::get().durations;
+        let work_secs = self.parse_secs(1, defaults.work_mins);
+        let short_break_secs = self.parse_secs(2, defaults.short_break_mins);
+        let long_break_secs = self.parse_secs(3, defaults.long_break_mins);
+        let long_break_interval = self.parse_interval(4, defaults.long_break_interval);
+        let project = parse_project(&std::mem::take(&mut self.0.inputs[5].text));
+        let tags = parse_tags([std::mem::take(&mut self.0.inputs[6].text).as_str()]);
+        // like the durations, anything unreadable is left at the default
+        let priority =
+            Priority::parse(&std::mem::take(&mut self.0.inputs[7].text)).unwrap_or_default();
+        let due = NaiveDate::parse_from_str(
+            std::mem::take(&mut self.0.inputs[8].text).trim(),
+            "%Y-%m-%d",
+        )
+        .ok();
+        let estimated_pomos = std::mem::take(&mut self.0.inputs[9].text)
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&pomos| pomos > 0);
+        Task {
+            desc: Some(std::mem::take(&mut self.0.inputs[0].text)),
             work_secs,
             short_break_secs,
             long_break_secs,
-        )
+            long_break_interval,
+            project,
+            tags,
+            priority,
+            due,
+            estimated_pomos,
+            ..Task::default()
+        }
     }
 }
 
@@ -554,8 +1315,8 @@ impl Focus for TaskTableGroup {
     }
 
     fn pre_move(&mut self) {
-        if self.focused.is_some() {
-            self.tables[self.focused.unwrap()].state.select(None)
+        if let Some(idx) = self.focused {
+            self.tables[idx].state.select(None)
         }
     }
 }
@@ -604,8 +1365,75 @@ impl TaskTableGroup {
         self.tables[self.focused?].selected()
     }
 
-    fn add_task(&mut self, task: Task) {
-        self.tables[0].tasks.push(task)
+    /// Adds a new task to the first table where a reload would put it: just
+    /// before the first top-level task it sorts ahead of, and its subtasks.
+    fn add_task(&mut self, task: Task, search: &Search) {
+        let table = &mut self.tables[0];
+        let at = table
+            .tasks
+            .iter()
+            .position(|(depth, other)| *depth == 0 && task.cmp_priority(other).is_lt())
+            .unwrap_or(table.tasks.len());
+        for (i, _) in &mut table.shown {
+            if *i >= at {
+                *i += 1;
+            }
+        }
+        if let Some(matched) = search.matches(&task) {
+            let row = table.shown.partition_point(|&(i, _)| i < at);
+            table.shown.insert(row, (at, matched));
+            // keep the same task selected
+            if let Some(selected) = table.state.selected().filter(|&s| s >= row) {
+                table.state.select(Some(selected + 1));
+            }
+        }
+        table.tasks.insert(at, (0, task))
+    }
+
+    /// Shows only the tasks matching `search` in each table, and selects the
+    /// first of them.
+    fn search(&mut self, search: &Search) {
+        for table in &mut self.tables {
+            table.search(search)
+        }
+        self.focused = None;
+        self.next_match()
+    }
+
+    /// Selects the next row shown, moving on to the next table after the last
+    /// row of one.
+    fn next_match(&mut self) {
+        self.step_match(true)
+    }
+
+    fn prev_match(&mut self) {
+        self.step_match(false)
+    }
+
+    fn step_match(&mut self, forward: bool) {
+        let rows: Vec<(usize, usize)> = self
+            .tables
+            .iter()
+            .enumerate()
+            .flat_map(|(i, table)| (0..table.shown.len()).map(move |row| (i, row)))
+            .collect();
+        if rows.is_empty() {
+            return;
+        }
+        let current = self
+            .focused
+            .and_then(|i| Some((i, self.tables[i].state.selected()?)))
+            .and_then(|selected| rows.iter().position(|&row| row == selected));
+        let next = match (current, forward) {
+            (Some(i), true) => (i + 1) % rows.len(),
+            (Some(i), false) => (i + rows.len() - 1) % rows.len(),
+            (None, true) => 0,
+            (None, false) => rows.len() - 1,
+        };
+        let (table, row) = rows[next];
+        self.pre_move();
+        self.focused = Some(table);
+        self.tables[table].state.select(Some(row));
     }
 }
 
@@ -613,23 +1441,39 @@ impl TaskTableGroup {
 struct TaskTable {
     state: TableState,
     title: String,
-    tasks: Vec<Task>,
+    /// In the order they're shown, each with how deeply it's nested.
+    tasks: Vec<(usize, Task)>,
+    /// The rows matching the search, as indices into `tasks`, each with the
+    /// characters of the task's name which matched.
+    shown: Vec<(usize, Vec<usize>)>,
 }
 
 impl TaskTable {
     fn new(tasks: Vec<Task>, title: String) -> Self {
+        let tasks = nest(tasks);
         TaskTable {
+            shown: (0..tasks.len()).map(|i| (i, Vec::new())).collect(),
             tasks,
             title,
             ..Default::default()
         }
     }
 
+    fn search(&mut self, search: &Search) {
+        self.shown = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, task))| Some((i, search.matches(task)?)))
+            .collect();
+        self.state.select(None)
+    }
+
     fn move_focus<F: Fn(usize) -> usize>(&mut self, f: F) {
         let selected = self.state.selected();
         let new_selected = if selected.is_some() {
             selected.map(f)
-        } else if !self.tasks.is_empty() {
+        } else if !self.shown.is_empty() {
             Some(0)
         } else {
             None
@@ -638,33 +1482,51 @@ impl TaskTable {
     }
 
     fn next(&mut self) {
-        let len = self.tasks.len();
+        let len = self.shown.len();
         self.move_focus(|i| (i + 1) % len)
     }
 
     fn previous(&mut self) {
-        let len = self.tasks.len();
+        let len = self.shown.len();
         self.move_focus(|i| if i == 0 { len - 1 } else { i - 1 })
     }
 
     fn selected(&self) -> Option<&Task> {
-        Some(&self.tasks[self.state.selected()?])
+        Some(&self.tasks[self.shown[self.state.selected()?].0].1)
     }
 
     pub fn render_on<B: Backend>(&mut self, frame: &mut Frame<'_, B>, chunk: Rect, focused: bool) {
-        let task_list = self.tasks.iter().map(|task| task.to_table_row());
+        // subtasks are only indented under their parents when nothing's hidden
+        let all_shown = self.shown.len() == self.tasks.len();
+        let task_list = self.shown.iter().map(|(i, matched)| {
+            let (depth, task) = &self.tasks[*i];
+            task.to_table_row(if all_shown { *depth } else { 0 }, matched)
+        });
+        let title = if all_shown {
+            self.title.clone()
+        } else {
+            format!("{} [{}/{}]", self.title, self.shown.len(), self.tasks.len())
+        };
 
-        let header_cells = ["Task", "Work", "Short break", "Long break"]
-            .iter()
-            .map(|&h| {
-                Cell::from(Text::styled(
-                    h,
-                    Style::default()
-                        .fg(Color::LightBlue)
-                        .add_modifier(Modifier::BOLD)
-                        .add_modifier(Modifier::ITALIC),
-                ))
-            });
+        let header_cells = [
+            "Task",
+            "Work",
+            "Short break",
+            "Long break",
+            "Set",
+            "Pomos",
+            "Due",
+        ]
+        .iter()
+        .map(|&h| {
+            Cell::from(Text::styled(
+                h,
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC),
+            ))
+        });
 
         let header = TableRow::new(header_cells)
             .bottom_margin(1)
@@ -678,19 +1540,147 @@ impl TaskTable {
             .header(header)
             .block(
                 Block::default()
-                    .title(Title::from(self.title.clone()).alignment(Alignment::Center))
+                    .title(Title::from(title).alignment(Alignment::Center))
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
                     .border_style(border_style),
             )
             .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red))
             .widths(&[
-                Constraint::Percentage(50),
-                Constraint::Percentage(16),
-                Constraint::Percentage(17),
-                Constraint::Percentage(17),
+                Constraint::Percentage(32),
+                Constraint::Percentage(10),
+                Constraint::Percentage(13),
+                Constraint::Percentage(13),
+                Constraint::Percentage(7),
+                Constraint::Percentage(11),
+                Constraint::Percentage(14),
             ]);
 
         frame.render_stateful_widget(task_list, chunk, &mut self.state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, parent_id: Option<u32>) -> Task {
+        Task {
+            id: Some(id),
+            parent_id,
+            ..Task::default()
+        }
+    }
+
+    /// `(depth, id)` of each task `nest` returns, in order.
+    fn nested(tasks: Vec<Task>) -> Vec<(usize, u32)> {
+        nest(tasks)
+            .into_iter()
+            .map(|(depth, task)| (depth, task.id.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn subtasks_follow_their_parents() {
+        let tasks = vec![
+            task(3, Some(1)),
+            task(1, None),
+            task(2, None),
+            task(4, Some(3)),
+            task(5, Some(1)),
+        ];
+        assert_eq!(nested(tasks), [(0, 1), (1, 3), (2, 4), (1, 5), (0, 2)]);
+    }
+
+    #[test]
+    fn subtasks_without_their_parent_are_roots() {
+        // e.g. when the parent is completed or filtered out
+        let tasks = vec![task(2, Some(1)), task(3, Some(2)), task(4, None)];
+        assert_eq!(nested(tasks), [(0, 2), (1, 3), (0, 4)]);
+    }
+
+    fn labelled(desc: &str, project: Option<&str>, tags: &[&str]) -> Task {
+        Task {
+            desc: Some(desc.into()),
+            project: project.map(Into::into),
+            tags: tags.iter().map(|&tag| tag.into()).collect(),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn search_splits_labels_from_the_pattern() {
+        let search = Search::parse("  +Acme wr  @Em rep ");
+        assert_eq!(search.projects, ["acme"]);
+        assert_eq!(search.tags, ["em"]);
+        assert_eq!(search.pattern, "wrrep".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn search_matches_ignoring_case() {
+        let task = labelled("Write Report", None, &[]);
+        assert_eq!(Search::parse("wRR").matches(&task), Some(vec![0, 1, 6]));
+        assert_eq!(Search::parse("").matches(&task), Some(vec![]));
+        // the characters have to come in order
+        assert_eq!(Search::parse("rw").matches(&task), None);
+    }
+
+    #[test]
+    fn search_labels_match_by_prefix() {
+        let task = labelled("report", Some("Acme"), &["work", "Email"]);
+        assert!(Search::parse("+ac").matches(&task).is_some());
+        assert!(Search::parse("@em @WO rep").matches(&task).is_some());
+        assert!(Search::parse("+me").matches(&task).is_none());
+        assert!(Search::parse("@home").matches(&task).is_none());
+        assert!(Search::parse("+acme rx").matches(&task).is_none());
+        assert!(Search::parse("+acme")
+            .matches(&labelled("report", None, &[]))
+            .is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_gives_first_positions_in_chars() {
+        let pattern: Vec<char> = "éa".chars().collect();
+        assert_eq!(fuzzy_match("cÉfé tea", &pattern), Some(vec![1, 7]));
+        assert_eq!(fuzzy_match("tea", &pattern), None);
+    }
+
+    #[test]
+    fn highlight_groups_runs() {
+        let spans = highlight("report", &[0, 1, 4]);
+        let runs: Vec<(&str, bool)> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style != Style::default()))
+            .collect();
+        assert_eq!(
+            runs,
+            [("re", true), ("po", false), ("r", true), ("t", false)]
+        );
+        assert_eq!(highlight("", &[]).len(), 0);
+        assert_eq!(highlight("report", &[]).len(), 1);
+    }
+
+    #[test]
+    fn added_tasks_are_sorted_in() {
+        let prioritised = |id, priority| Task {
+            priority,
+            ..task(id, None)
+        };
+        let mut group = TaskTableGroup::new(vec![(
+            vec![
+                prioritised(1, Priority::High),
+                task(2, Some(1)),
+                prioritised(3, Priority::Low),
+            ],
+            String::new(),
+        )]);
+        let search = Search::parse("");
+        group.add_task(prioritised(4, Priority::Medium), &search);
+        group.add_task(prioritised(5, Priority::Low), &search);
+        let table = &group.tables[0];
+        let ids: Vec<u32> = table.tasks.iter().map(|(_, t)| t.id.unwrap()).collect();
+        assert_eq!(ids, [1, 2, 4, 3, 5]);
+        let shown: Vec<usize> = table.shown.iter().map(|(i, _)| *i).collect();
+        assert_eq!(shown, [0, 1, 2, 3, 4]);
+    }
+}
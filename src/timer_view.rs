@@ -0,0 +1,382 @@
+use crate::{
+    config,
+    cycle_log::NotePrompt,
+    daemon::{self, Request, Status},
+    db::{Interruption, Interruptions},
+    pomodoro::{centered_rect, format_remaining, PomodoroState},
+    states::{AppResult, State},
+    stats::format_secs,
+    tasks::TasksState,
+};
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::{cmp::max, io};
+use tui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    prelude::CrosstermBackend,
+    style::{Color, Style},
+    text::Line,
+    widgets::{block::Title, Block, BorderType, Borders, Clear, Gauge, Paragraph},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+const POMO_HEIGHT: u16 = 5;
+const POMO_WIDTH: u16 = 34;
+const HELP_TEXT: &str = "[p] - toggle pause on current pomo
+[n] - skip to next cycle in pomo
+[+] / [>] - add 1 / 5 minutes to current cycle
+[<] - take 1 minute off current cycle
+['] / [-] - log an internal / external interruption
+[q] - quit, leaving the pomo running in the background
+[esc] - stop the pomo and return to tasks page
+[enter] - start next cycle once one is over,
+          otherwise complete task and return to tasks page
+[?] - toggle this help page";
+const STOPPED_TEXT: &str = "The pomo was stopped elsewhere.
+Press any key to return to tasks page.";
+
+/// Shows the pomo running in the daemon, and passes keys on to it as requests.
+pub struct TimerView {
+    /// `None` once the pomo has been stopped, e.g. from another shell.
+    status: Option<Status>,
+    /// Asking what got done in the work cycle which just ended.
+    note: Option<NotePrompt>,
+    /// Interruption being logged, with the reason typed so far.
+    interruption: Option<(Interruption, String)>,
+    /// What went wrong with the last request to the daemon.
+    error: Option<String>,
+    show_help: bool,
+    should_finish: bool,
+}
+
+#[async_trait]
+impl State for TimerView {
+    async fn tick(&mut self) -> AppResult<()> {
+        // the daemon may have been killed or restarted, so there's nothing left to show
+        if self.status.is_some() && !self.send(Request::Status).await {
+            self.status = None;
+        }
+        Ok(())
+    }
+
+    fn should_finish(&self) -> bool {
+        self.should_finish
+    }
+
+    fn render(&mut self, frame: &mut Frame<'_, CrosstermBackend<io::Stderr>>) {
+        let Some(status) = &self.status else {
+            let text = match &self.error {
+                Some(err) => format!(
+                    "Lost the pomo: {}\nPress any key to return to tasks page.",
+                    err
+                ),
+                None => STOPPED_TEXT.into(),
+            };
+            let width = text.lines().map(|line| line.width()).max().unwrap_or(0) + 2;
+            let chunk = centered_rect(width as u16, 4, frame.size());
+            let text = Paragraph::new(text).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            );
+            frame.render_widget(text, chunk);
+            return;
+        };
+        if self.show_help {
+            let help_chunk = centered_rect(58, 12, frame.size());
+            let help_text = Paragraph::new(HELP_TEXT)
+                .block(
+                    Block::default()
+                        .title("Help")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(help_text, help_chunk);
+            return;
+        }
+        let style = status.phase.style();
+        let progress = match status.estimated_pomos {
+            Some(estimate) if status.pomos_finished > estimate => {
+                format!("{}/{}, over estimate!", status.pomos_finished, estimate)
+            }
+            Some(estimate) => format!(
+                "{}/{}{}",
+                status.pomos_finished,
+                estimate,
+                status.eta_secs.map_or(String::new(), |secs| format!(
+                    ", done in ~{}",
+                    // rounded up, so the last minute doesn't read as 0m
+                    format_secs(secs.div_ceil(60) as i64 * 60)
+                ))
+            ),
+            None => status.pomos_finished.to_string(),
+        };
+        let pomo_text = format!(
+            "Finished: {} ({}/{} in this set)",
+            progress, status.set_position, status.long_break_interval
+        );
+        let (height, width) = if let Some(desc) = &status.task {
+            (
+                POMO_HEIGHT + 1,
+                max(
+                    (desc.width() + "Working on: ".width()).max(pomo_text.width()) as u16 + 2,
+                    POMO_WIDTH,
+                ),
+            )
+        } else {
+            (POMO_HEIGHT, max(pomo_text.width() as u16 + 2, POMO_WIDTH))
+        };
+
+        let pomo_chunk = centered_rect(width, height, frame.size());
+
+        let pause_text = if status.waiting {
+            " over"
+        } else if status.paused {
+            " — paused"
+        } else {
+            ""
+        };
+
+        frame.render_widget(
+            Block::default()
+                .title(format!("{}{}", status.phase, pause_text))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(style),
+            pomo_chunk,
+        );
+
+        // split into info and gauge
+        let pomo_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(if status.task.is_some() { 2 } else { 1 }),
+                Constraint::Length(2),
+            ])
+            .margin(1)
+            .split(pomo_chunk);
+
+        let pomo_style = if status
+            .estimated_pomos
+            .is_some_and(|estimate| status.pomos_finished > estimate)
+        {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        let mut lines = Vec::new();
+        if let Some(desc) = &status.task {
+            lines.push(Line::from(format!("Working on: {}", desc)));
+        }
+        lines.push(Line::styled(pomo_text, pomo_style));
+        let pomo_par = Paragraph::new(lines).alignment(Alignment::Left);
+
+        frame.render_widget(pomo_par, pomo_chunks[0]);
+
+        let gauge_title = if status.waiting {
+            format!(
+                "Press Enter to start {}",
+                status.next_phase.to_string().to_lowercase()
+            )
+        } else {
+            format!("Remaining: {}", format_remaining(status.remaining_secs))
+        };
+        let mut gauge_block = Block::default().title(gauge_title);
+        let Interruptions { internal, external } = status.interruptions;
+        if internal + external > 0 {
+            gauge_block = gauge_block.title(
+                Title::from(format!("{}' {}-", internal, external)).alignment(Alignment::Right),
+            );
+        }
+        let gauge = Gauge::default()
+            .block(gauge_block)
+            .gauge_style(style)
+            .ratio(status.elapsed_secs as f64 / status.duration_secs.max(1) as f64)
+            .use_unicode(true);
+
+        frame.render_widget(gauge, pomo_chunks[1]);
+
+        let area = frame.size();
+        let width = max(width, 60).min(area.width);
+        let below = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: (pomo_chunk.y + pomo_chunk.height + 1).min(area.height.saturating_sub(3)),
+            width,
+            height: 3.min(area.height),
+        };
+        if let Some(note) = &self.note {
+            note.render_on(frame, below);
+        } else if let Some((kind, reason)) = &self.interruption {
+            let prompt = Paragraph::new(reason.as_str())
+                .style(Style::default().fg(Color::Yellow))
+                .block(
+                    Block::default()
+                        .title(format!(
+                            "Reason for {} interruption? [enter] log, [esc] cancel",
+                            kind.as_str()
+                        ))
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                );
+            frame.render_widget(Clear, below);
+            frame.render_widget(prompt, below);
+            frame.set_cursor(below.x + reason.width() as u16 + 1, below.y + 1);
+        } else if let Some(err) = &self.error {
+            let error = Paragraph::new(err.as_str())
+                .style(Style::default().fg(Color::Red))
+                .block(
+                    Block::default()
+                        .title("Error")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                );
+            frame.render_widget(Clear, below);
+            frame.render_widget(error, below);
+        }
+    }
+
+    async fn handle_key_event(mut self: Box<Self>, event: KeyEvent) -> AppResult<Box<dyn State>> {
+        let Some(status) = &self.status else {
+            return Ok(Box::new(TasksState::new().await?));
+        };
+        if let Some(note) = &mut self.note {
+            if note.handle_key(event).await? {
+                self.note = None;
+            }
+            return Ok(self);
+        }
+        if let Some((kind, reason)) = &mut self.interruption {
+            match event.code {
+                KeyCode::Char(c) => reason.push(c),
+                KeyCode::Backspace => {
+                    reason.pop();
+                }
+                KeyCode::Enter => {
+                    let kind = *kind;
+                    let reason = reason.trim().to_string();
+                    self.interruption = None;
+                    self.send(Request::Interrupt {
+                        kind,
+                        reason: (!reason.is_empty()).then_some(reason),
+                    })
+                    .await;
+                }
+                KeyCode::Esc => self.interruption = None,
+                _ => {}
+            }
+            return Ok(self);
+        }
+        match event.code {
+            KeyCode::Char('p') => {
+                let request = if status.paused {
+                    Request::Resume
+                } else {
+                    Request::Pause
+                };
+                self.send(request).await;
+            }
+            KeyCode::Char('n') => {
+                self.send(Request::Skip).await;
+            }
+            KeyCode::Char('+') => {
+                self.send(Request::Extend { secs: 60 }).await;
+            }
+            KeyCode::Char('>') => {
+                self.send(Request::Extend { secs: 300 }).await;
+            }
+            KeyCode::Char('<') => {
+                self.send(Request::Extend { secs: -60 }).await;
+            }
+            KeyCode::Char('\'') if status.phase == PomodoroState::Work && !status.waiting => {
+                self.interruption = Some((Interruption::Internal, String::new()))
+            }
+            KeyCode::Char('-') if status.phase == PomodoroState::Work && !status.waiting => {
+                self.interruption = Some((Interruption::External, String::new()))
+            }
+            // the daemon keeps the pomo going after the UI exits
+            KeyCode::Char('q') => self.should_finish = true,
+            KeyCode::Enter if status.waiting => {
+                self.send(Request::Skip).await;
+            }
+            KeyCode::Enter => {
+                let id = status.task_id;
+                if self.send(Request::Complete).await {
+                    let tasks = TasksState::new().await?;
+                    return Ok(Box::new(match id {
+                        Some(id) => tasks.offer_parent(id as i64).await?,
+                        None => tasks,
+                    }));
+                }
+            }
+            KeyCode::Esc => {
+                let stopped = self.send(Request::Stop).await;
+                if stopped {
+                    return Ok(Box::new(TasksState::new().await?));
+                }
+            }
+            KeyCode::Char('?') => {
+                // pause while the help is up, and carry on once it's closed
+                if self.show_help {
+                    self.send(Request::Resume).await;
+                } else if !status.paused {
+                    self.send(Request::Pause).await;
+                }
+                self.show_help = !self.show_help
+            }
+            _ => {}
+        }
+        Ok(self)
+    }
+}
+
+impl TimerView {
+    /// Asks the daemon to start a pomo, starting the daemon first if needed.
+    pub async fn start(request: Request) -> AppResult<Self> {
+        daemon::ensure_running().await?;
+        Ok(Self::attach(daemon::send(&request).await?))
+    }
+
+    /// Shows a pomo which is already running in the daemon.
+    pub fn attach(status: Option<Status>) -> Self {
+        Self {
+            status,
+            note: None,
+            interruption: None,
+            error: None,
+            show_help: false,
+            should_finish: false,
+        }
+    }
+
+    /// Sends `request` to the daemon, showing what went wrong rather than
+    /// failing if it didn't go through. Returns whether it did.
+    async fn send(&mut self, request: Request) -> bool {
+        match daemon::send(&request).await {
+            Ok(status) => {
+                self.error = None;
+                self.update(status);
+                true
+            }
+            Err(err) => {
+                self.error = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Takes in the latest status, asking for a note if a work cycle has just
+    /// ended.
+    fn update(&mut self, status: Option<Status>) {
+        if let (Some(old), Some(new)) = (&self.status, &status) {
+            let work_ended = old.phase == PomodoroState::Work
+                && !old.waiting
+                && (new.phase != PomodoroState::Work || new.waiting);
+            if work_ended && self.note.is_none() && config::get().ui.note_prompt {
+                self.note = Some(NotePrompt::new(old.cycle_id, String::new()));
+            }
+        }
+        self.status = status;
+    }
+}